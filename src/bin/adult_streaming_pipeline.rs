@@ -0,0 +1,35 @@
+use std::time::Instant;
+use differential_dataflow::input::InputSession;
+use IncrementalFeatureEncoders::feature_encoders::column_encoder::ColumnEncoder;
+use IncrementalFeatureEncoders::feature_encoders::multi_column_encoder::{multi_column_encoder_with_schema, EncoderFactory};
+use IncrementalFeatureEncoders::feature_encoders::one_hot_encoder::OneHotEncoder;
+use IncrementalFeatureEncoders::feature_encoders::standard_scaler::StandardScaler;
+use IncrementalFeatureEncoders::pipelines::csv_reader::{CsvReader, NullPolicy};
+use IncrementalFeatureEncoders::pipelines::csv_source::{BatchPolicy, CsvSource};
+use IncrementalFeatureEncoders::types::schema::{ColumnSelector, LogicalType};
+
+// streams data/adult_data.csv, every worker reads its own part of the file:
+// cargo run --release --bin adult-streaming-pipeline -- -w 4
+fn main() {
+    println!("ADULT STREAMING PIPELINE\n");
+    timely::execute_from_args(std::env::args(), move |worker| {
+        let reader = CsvReader { null_policy: NullPolicy::FillText("?".to_string()), ..CsvReader::new() };
+        let mut source = CsvSource::new_with_reader("data/adult_data.csv", worker.index(), worker.peers(), BatchPolicy::Rows(1000), reader)
+            .expect("could not read data/adult_data.csv");
+        let schema = source.schema().clone();
+        let mut input = InputSession::new();
+        let probe = worker.dataflow(|scope| {
+            let excluded = ColumnSelector::Name("salary".to_string());
+            let config: Vec<(ColumnSelector, EncoderFactory<_>)> = vec![
+                (ColumnSelector::Except(Box::new(ColumnSelector::Type(LogicalType::Text)), Box::new(excluded)),
+                 Box::new(|| Box::new(OneHotEncoder::new()) as Box<dyn ColumnEncoder<_>>)),
+                (ColumnSelector::Numeric, Box::new(|| Box::new(StandardScaler::new()) as Box<dyn ColumnEncoder<_>>)),
+            ];
+            multi_column_encoder_with_schema(&input.to_collection(scope), &schema, config)
+                .probe()
+        });
+        let timer = Instant::now();
+        source.feed_all(worker, input, &probe).expect("could not read data/adult_data.csv");
+        println!("\nWorker {} took: {:?}, malformed rows: {}", worker.index(), timer.elapsed(), source.malformed().len());
+    }).expect("Computation terminated abnormally");
+}
@@ -0,0 +1,7 @@
+use IncrementalFeatureEncoders::pipelines::parallel_fit_benchmark::run_parallel_fit_benchmark;
+
+// fits the adult dataset in data/adult_data.csv with 1, 2, 4 and 8 workers:
+// cargo run --release --bin parallel-fit-benchmark
+fn main() {
+    run_parallel_fit_benchmark("data/adult_data.csv", vec![1, 2, 4, 8], 0.9);
+}
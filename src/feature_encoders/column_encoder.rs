@@ -1,6 +1,7 @@
 use differential_dataflow::Collection;
 use differential_dataflow::lattice::Lattice;
 use timely::dataflow::{Scope};
+use crate::feature_encoders::fitted_params::ParamRecord;
 use crate::types::row_value::RowValue;
 
 pub trait ColumnEncoder<G: Scope>
@@ -12,4 +13,32 @@ where
 
     /// Transforms the input data using the internally stored metadata
     fn transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G,  (usize, RowValue)>;
+
+    /// Names of the output positions as (position, name) pairs, given the names of the input positions
+    /// (a single (0, name) pair for a plain column). The default keeps the input names, which fits encoders
+    /// that keep every position where it is
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        input_names.clone()
+    }
+
+    /// Width of the output vectors given the width of the input vectors. None means the width is the length of the
+    /// longest output vector
+    fn output_width(&self, _input_width: Option<Collection<G, usize>>) -> Option<Collection<G, usize>> {
+        None
+    }
+
+    /// Lets an encoder with an output_width emit rows that end at their last non-zero position (see RowValue::cells)
+    /// instead of rows of the full width, so a growing width doesn't touch the rows. The ColumnTransformer calls it
+    /// for its encoders, as it pads every segment to the output_width itself. The default keeps the rows as they are
+    fn emit_cells(&mut self) {
+    }
+
+    /// Returns the fitted metadata as a collection of records, None if the encoder keeps no fitted state
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        None
+    }
+
+    /// Uses the given records (e.g. from FittedParams::to_collection) as metadata, transform then works without fit
+    fn import_params(&mut self, _params: &Collection<G, ParamRecord>) {
+    }
 }
@@ -0,0 +1,122 @@
+use differential_dataflow::Collection;
+use differential_dataflow::lattice::Lattice;
+use timely::dataflow::{Scope};
+use crate::feature_encoders::fitted_params::{export_positions, import_positions, ParamRecord};
+use crate::feature_encoders::multi_input_encoder::{crossed_name, MultiInputEncoder};
+use crate::feature_encoders::one_hot_encoder::{fit_positions, one_hot, pad_to_width, position_names};
+use crate::types::row_value::RowValue;
+use crate::types::safe_hash_map::SafeHashMap;
+
+// FeatureCross one-hot encodes the cartesian product of categorical columns,
+// only combinations that have been observed are assigned a position
+pub struct FeatureCross<G: Scope> {
+    value_positions: Option<Collection<G, ((), (SafeHashMap<Vec<RowValue>, usize>, usize))>>,
+    cells: bool,
+}
+
+impl<G: Scope> FeatureCross<G> {
+    pub fn new() -> Self<>{
+        Self{value_positions:None, cells:false}
+    }
+}
+
+impl<G: Scope> MultiInputEncoder<G> for FeatureCross<G>
+where G::Timestamp: Lattice+Ord {
+    fn fit(&mut self, data: &Collection<G, (usize, Vec<RowValue>)>) {
+        self.value_positions = Some(fit_positions(&data.map(|(_, values)| values), None));
+    }
+
+    fn transform(&self, data: &Collection<G, (usize, Vec<RowValue>)>) -> Collection<G, (usize, RowValue)> {
+        let value_positions = match &self.value_positions {
+            None => panic!("called transform before fit"),
+            Some(m) => m
+        };
+        let encoded = one_hot(data, value_positions);
+        if self.cells { encoded } else { pad_to_width(&encoded, &value_positions.map(|(_, (_, len))| len)) }
+    }
+
+    fn emit_cells(&mut self) {
+        self.cells = true;
+    }
+
+    fn output_width(&self) -> Option<Collection<G, usize>> {
+        self.value_positions.as_ref().map(|m| m.map(|(_, (_, len))| len))
+    }
+
+    // the combinations are stored as RowValue::Row
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        self.value_positions.as_ref().map(|m| export_positions(m, RowValue::Row))
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        self.value_positions = Some(import_positions(params, |value| match value {
+            RowValue::Row(values) => values,
+            _ => panic!("feature cross positions need RowValue::Row keys"),
+        }));
+    }
+
+    // a_x_b=value of a_value of b for every position
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        let value_positions = match &self.value_positions {
+            None => panic!("called feature_names before fit"),
+            Some(m) => m
+        };
+        position_names(&crossed_name(input_names), value_positions, "=",
+            |values| values.iter().map(|v| v.label()).collect::<Vec<_>>().join("_"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use crate::feature_encoders::multi_column_encoder::ColumnTransformer;
+    use crate::feature_encoders::row_encoder::RowEncoder;
+    use crate::types::row::Row;
+    use super::*;
+    #[test]
+    fn feature_cross_encodes_observed_pairs() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+        let result = timely::execute(timely::Config::process(2), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::clone(&output_clone);
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = ColumnTransformer::new(vec![])
+                    .with_multi_input(vec![(vec![0, 2], Box::new(FeatureCross::new()) as Box<dyn MultiInputEncoder<_>>)]);
+                enc.fit_rows(&input_df);
+
+                enc.transform_vectors(&input_df)
+                    .inspect(move |((ix, x), _, diff)| {
+                        output.lock().unwrap().push((*ix, x.to_dense().get_vec().clone(), *diff));
+                    })
+                    .probe()
+            });
+
+            input.advance_to(0);
+            if worker.index() == 0 {
+                let pairs = [("a", "x"), ("a", "y"), ("b", "x"), ("a", "x")];
+                for (ix, (a, b)) in pairs.iter().enumerate() {
+                    input.insert((ix, Row::with_row_values(vec![
+                        RowValue::Text(a.to_string()), RowValue::Integer(ix as i64), RowValue::Text(b.to_string())
+                    ])));
+                }
+            }
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+
+        let mut output = output.lock().unwrap().clone();
+        output.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!(output.iter().all(|(_, _, diff)| *diff == 1), "Rows were encoded more than once");
+        let vectors: Vec<Vec<f64>> = output.into_iter().map(|(_, x, _)| x).collect();
+        // three observed pairs, the repeated pair shares its position
+        assert_eq!(vectors.len(), 4, "Transformed output is incorrect");
+        assert!(vectors.iter().all(|x| x.len() == 3 && x.iter().sum::<f64>() == 1.0), "Vectors are not one-hot");
+        assert_eq!(vectors[0], vectors[3], "Equal pairs are encoded differently");
+        assert!(vectors[0] != vectors[1] && vectors[0] != vectors[2] && vectors[1] != vectors[2], "Different pairs share a position");
+    }
+}
@@ -1,39 +1,56 @@
 use differential_dataflow::Collection;
 use differential_dataflow::lattice::Lattice;
-use differential_dataflow::operators::{Count, Join, Threshold};
+use differential_dataflow::operators::{Count, Join, Reduce, Threshold};
 use timely::dataflow::{Scope};
 use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::operators::parallel_fit::assign_positions;
 use crate::types::row_value::RowValue;
 use crate::types::row_value::RowValue::Text;
 use crate::feature_encoders::feature_extraction::utils::{default_tokenizer};
+use crate::feature_encoders::fitted_params::{export_positions, import_positions, ParamRecord};
+use crate::feature_encoders::one_hot_encoder::{pad_to_width, position_names};
 use crate::types::safe_hash_map::SafeHashMap;
-use crate::types::integer_assignment_aggregate::PositionAssignmentAggregate;
+use crate::types::integer_assignment_aggregate::{PositionAssignmentAggregate, WidthPolicy};
 
 pub struct CountVectorizer <G: Scope> {
     corpus: Option<Collection<G, ((), (SafeHashMap<String, usize>, usize))>>, //(HashMap<Token -> Index, max_index)
-    binary: bool
+    binary: bool,
+    width_policy: Option<WidthPolicy>,
+    cells: bool,
 }
 
 impl<G: Scope> CountVectorizer<G> {
     pub fn new(binary : bool) -> Self<>{
-        Self{corpus:None, binary}
+        Self{corpus:None, binary, width_policy:None, cells:false}
+    }
+
+    // with a fixed or growing-only width, documents are only re-emitted when a token they contain changes or the width grows
+    pub fn new_with_width_policy(binary : bool, width_policy: WidthPolicy) -> Self<>{
+        Self{corpus:None, binary, width_policy:Some(width_policy), cells:false}
+    }
+
+    // the current vector width, a resize shows up as a retraction of the old and an insertion of the new width
+    pub fn widths(&self) -> Collection<G, usize> {
+        match &self.corpus {
+            None => panic!("called widths before fit"),
+            Some(c) => c.map(|(_, (_, len))| len)
+        }
     }
 }
 
 impl<G: Scope> ColumnEncoder<G> for CountVectorizer<G>
 where G::Timestamp: Lattice+Ord {
     fn fit(&mut self, data: &Collection<G, (usize, RowValue)>) {
-        let tokenized = data
-            .map(|(_, val)| {
+        let tokens = data
+            .flat_map(|(_, val)| {
                 match val {
                     Text(text) => {default_tokenizer(&text)},
                     _ => !panic!("count vectorizer called on non-text column")
                 }
             });
-        self.corpus = Some(tokenized
-            .threshold(|tokens, multiplicity|
-                PositionAssignmentAggregate::new_with_vec(tokens, *multiplicity))
-            .map(|_vector| ()).count()
+        let width_policy = self.width_policy;
+        self.corpus = Some(assign_positions(&tokens.map(|token| ((), token)), move |token, multiplicity|
+                PositionAssignmentAggregate::new_with_val(token, multiplicity, width_policy))
             .map(|((), agg)| ((), agg.get_map_and_len())));
     }
 
@@ -44,27 +61,148 @@ where G::Timestamp: Lattice+Ord {
         };
         let binary = self.binary.clone();
 
-        data.map(|(id, val)| {
-            let tokens : Vec<String>= match val {
-                Text(text) => {default_tokenizer(&text)}
-                _ => !panic!("count vectorizer called on non-text column")
-            };
-            ((), (id, tokens))
-        }).join(&corpus).map(move |(_, ((id, tokens), (word_to_index, len)))| {
-            let mut vec = vec![0f64; len];
-            for token in tokens {
-                let i = word_to_index.get(&token);
-                if let Some(i) = i {
-                    if binary {
-                        vec[*i] = 1.0;
-                    } else {
-                        vec[*i] += 1.0;
-                    }
-                } else{
-                    //token not in corpus
-                }
-            }
-            (id, RowValue::Vec(vec))
-        })
+        // (token, index) pairs, the pairs of unchanged tokens cancel out when the corpus changes,
+        // so a new word only reaches the documents that contain it
+        let token_index = corpus.flat_map(|(_, (word_to_index, _len))| word_to_index.0.into_iter());
+
+        let positions = data
+            .flat_map(|(id, val)| {
+                let tokens : Vec<String>= match val {
+                    Text(text) => {default_tokenizer(&text)}
+                    _ => panic!("count vectorizer called on non-text column")
+                };
+                tokens.into_iter().map(move |token| (token, id))
+            })
+            .join(&token_index)
+            .map(|(_token, (id, index))| (id, index));
+        let positions = if binary { positions.distinct() } else { positions };
+        let counts = positions.count()
+            .map(|((id, index), count)| (id, Some((index, count))));
+
+        // documents without any known token still get an (empty) vector. with emit_cells the width is not part of
+        // the documents, so a growing vocabulary doesn't touch the documents without the new tokens
+        let documents = data.map(|(id, _)| (id, None))
+            .concat(&counts)
+            .reduce(|_id, input, output| {
+                let entries: Vec<(usize, f64)> = input.iter()
+                    .filter_map(|(entry, _)| entry.map(|(index, count)| (index, count as f64)))
+                    .collect();
+                output.push((RowValue::cells(entries), 1isize));
+            });
+        if self.cells { documents } else { pad_to_width(&documents, &self.widths()) }
+    }
+
+    fn emit_cells(&mut self) {
+        self.cells = true;
+    }
+
+    fn output_width(&self, _input_width: Option<Collection<G, usize>>) -> Option<Collection<G, usize>> {
+        self.corpus.as_ref().map(|_| self.widths())
+    }
+
+    // the tokens are stored as RowValue::Text
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        self.corpus.as_ref().map(|c| export_positions(c, Text))
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        self.corpus = Some(import_positions(params, |value| match value {
+            Text(token) => token,
+            _ => panic!("count vectorizer positions need RowValue::Text keys"),
+        }));
+    }
+
+    // column:token for every position
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        let corpus = match &self.corpus {
+            None => panic!("called feature_names before fit"),
+            Some(c) => c
+        };
+        position_names(&input_names.map(|(_pos, name)| ((), name)), corpus, ":", |token| token.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use super::*;
+    #[test]
+    fn count_vectorizer_works() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = CountVectorizer::new(false);
+                enc.fit(&input_df);
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+
+                enc.transform(&input_df)
+                    .inspect(move |((ix, x), _, diff)| {
+                        let mut out = output_clone.lock().unwrap();
+                        // (row id, number of distinct tokens, number of tokens)
+                        let entries = x.nonzero_entries();
+                        out.push((*ix, entries.len(), entries.iter().map(|(_, c)| *c).sum::<f64>(), *diff));
+                    })
+                    .probe()
+            });
+
+            input.advance_to(0);
+            input.insert((0, Text("a b a".to_string())));
+            input.insert((1, Text("b".to_string())));
+
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // lock the Mutex to access data
+            let mut output = output.lock().unwrap();
+            output.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+            // Check the output
+            assert_eq!(&*output, &vec![(0, 2, 3.0, 1), (1, 1, 1.0, 1)], "Transformed output is incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+
+    #[test]
+    fn new_tokens_only_reach_their_documents() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let widths = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = CountVectorizer::new_with_width_policy(false, WidthPolicy::Exact);
+                enc.emit_cells();
+                enc.fit(&input_df);
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+                let widths_clone = Arc::clone(&widths);
+
+                enc.widths()
+                    .inspect(move |(len, time, diff)| widths_clone.lock().unwrap().push((*time, *len, *diff)));
+                enc.transform(&input_df)
+                    .inspect(move |((ix, _), time, _)| output_clone.lock().unwrap().push((*time, *ix)))
+                    .probe()
+            });
+
+            input.advance_to(0);
+            input.insert((0, Text("a b".to_string())));
+            input.insert((1, Text("b".to_string())));
+            input.advance_to(1);
+            input.insert((2, Text("c".to_string())));
+            input.advance_to(2);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // the width grows, the documents without the new token are not sent again
+            let output = output.lock().unwrap();
+            assert!(output.iter().all(|(time, ix)| *time == 0 || *ix == 2), "Unchanged documents were sent again");
+            let mut widths = widths.lock().unwrap();
+            widths.sort();
+            assert_eq!(&*widths, &vec![(0, 2, 1), (1, 2, -1), (1, 3, 1)], "Widths are incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
     }
 }
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::hash::{DefaultHasher, Hash, Hasher};
 use differential_dataflow::Collection;
 use differential_dataflow::lattice::Lattice;
@@ -31,19 +32,26 @@ where G::Timestamp: Lattice+Ord {
                 Text(s) => s,
                 _ => panic!("can only apply to text features"),
             };
-            let mut vec = vec![0f64; n_features];
+            let mut counts = BTreeMap::new();
             let tokens = default_tokenizer(&text);
             for token in tokens {
                 let mut hasher = DefaultHasher::new();
                 token.hash(&mut hasher);
                 let hash_value = hasher.finish() as usize % n_features;
+                let count = counts.entry(hash_value).or_insert(0f64);
                 if binary {
-                    vec[hash_value] = 1.0;
+                    *count = 1.0;
                 } else {
-                    vec[hash_value] += 1.0;
+                    *count += 1.0;
                 }
             }
-            (i, RowValue::Vec(vec))
+            (i, RowValue::sparse(n_features, counts.into_iter().collect()))
         })
     }
+
+    // the buckets are shared by all tokens that hash to them, so they are only numbered
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        let n_features = self.n_features;
+        input_names.flat_map(move |(_pos, name)| (0..n_features).map(move |i| (i, format!("{}:hash_{}", name, i))))
+    }
 }
\ No newline at end of file
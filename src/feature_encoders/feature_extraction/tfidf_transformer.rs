@@ -2,23 +2,41 @@ use std::cmp::max;
 use differential_dataflow::Collection;
 use differential_dataflow::difference::{Abelian, IsZero, Monoid, Semigroup};
 use differential_dataflow::lattice::Lattice;
-use differential_dataflow::operators::{Count, Join, Threshold};
 use serde::{Deserialize, Serialize};
 use timely::dataflow::{Scope};
 use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::feature_encoders::fitted_params::ParamRecord;
+use crate::operators::broadcast_join::broadcast_join;
+use crate::operators::parallel_fit::{by_row_id, partitioned_aggregate};
+use crate::operators::republish::{republish, RepublishPolicy};
 use crate::types::row_value::RowValue;
+use crate::types::safe_f64::SafeF64;
 
 pub struct TfidfTransformer<G: Scope> {
-    frequencies : Option<Collection<G, ((), DocumentFrequencyAggregate)>>,
-    round_to: Option<i32>,
+    // [number of documents, number of documents with a non-zero value at every position]
+    frequencies : Option<Collection<G, ((), Vec<SafeF64>)>>,
+    staleness: Option<Collection<G, ((), usize)>>,
+    republish_policy: RepublishPolicy,
 }
 
 impl<G: Scope> TfidfTransformer<G> {
     pub fn new() -> TfidfTransformer<G> {
-        Self{frequencies:None, round_to:None}
+        Self::new_with_republish_policy(RepublishPolicy::Always)
     }
+    // the document count is kept exact, the frequencies are rounded to 10^n
     pub fn new_with_rounding(n: i32) -> Self{
-        Self{frequencies:None, round_to: Some(n)}
+        Self::new_with_republish_policy(RepublishPolicy::DecimalRounding(vec![0, n]))
+    }
+    pub fn new_with_republish_policy(republish_policy: RepublishPolicy) -> Self{
+        Self{frequencies:None, staleness:None, republish_policy}
+    }
+
+    // number of changes of the document frequencies that the republish policy holds back
+    pub fn staleness(&self) -> Collection<G, ((), usize)> {
+        match &self.staleness {
+            None => panic!("called staleness before fit"),
+            Some(s) => s.clone()
+        }
     }
 }
 
@@ -27,27 +45,35 @@ impl<G: Scope> TfidfTransformer<G> {
 impl<G: Scope> ColumnEncoder<G> for TfidfTransformer<G>
 where G::Timestamp: Lattice+Ord {
     fn fit(&mut self, data: &Collection<G, (usize, RowValue)>) {
-        let round_to = self.round_to.clone();
         let transformed = data
-            .map(|(_, vector)| {
+            .map(|(ix, vector)| {
                 match &vector {
-                    RowValue::Vec(v) => {
+                    RowValue::Vec(_) | RowValue::Sparse { .. } => {
+                        // only the non-zero positions are counted, so documents that end at their last token
+                        // (see RowValue::cells) count the same as padded ones
                         let epsilon = 1e-10;
-                        let v: Vec<isize> = v.iter()
-                            .map(|&x| if (x - 0.0).abs() < epsilon { 1 } else { 0 })
-                            .collect();
-                        return v;
+                        let mut v = vec![0isize; vector.vector_len()];
+                        for (i, x) in vector.nonzero_entries() {
+                            if (x - 0.0).abs() >= epsilon {
+                                v[i] = 1;
+                            }
+                        }
+                        return ((), (ix, v));
                     }
                     _ => panic!("this should not happen in theory (backend doesnt yield Vec)")
                 };
             });
-        let frequencies = transformed
-            .threshold(move |vector, multiplicity| {
-                DocumentFrequencyAggregate::new(vector.clone(), *multiplicity, round_to)
+        let frequencies = partitioned_aggregate(&transformed, by_row_id, |(_ix, vector), multiplicity| {
+                DocumentFrequencyAggregate::new(vector.clone(), multiplicity)
             })
-            .map(|_vector| ())
-            .count();
+            .flat_map(|((), agg)| agg.get_frequencies().map(|freqs| {
+                let mut params = vec![SafeF64(agg.get_count() as f64)];
+                params.extend(freqs.into_iter().map(|f| SafeF64(f as f64)));
+                ((), params)
+            }));
+        let (frequencies, staleness) = republish(&frequencies, self.republish_policy.clone());
         self.frequencies = Some(frequencies);
+        self.staleness = Some(staleness);
     }
 
     fn transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
@@ -55,35 +81,54 @@ where G::Timestamp: Lattice+Ord {
             None => panic!("called transform before fit"),
             Some(f) => f
         };
-        data
-            .map(|x| ((), x))
-            .join(&frequencies)
-            .map(|(_, ((id, dense), frequencies))| {
-                let freq_vector = match frequencies.get_frequencies() {
-                    None => panic!("this should not happen in theory (would mean that the aggregate is empty)"),
-                    Some(v) => v
-                };
-                let doc = match &dense {
-                    RowValue::Vec(v) => v,
+        broadcast_join(&data.map(|x| ((), x)), frequencies)
+            .map(|(_, ((id, doc), params))| {
+                let count = params[0].0;
+                let nonzero_counts = &params[1..];
+                let len = match &doc {
+                    RowValue::Vec(_) | RowValue::Sparse { .. } => doc.vector_len(),
                     _ => panic!("this should not happen in theory (backend doesnt yield Vec)"),
                 };
+                // the documents with a zero at i, positions beyond the counts are zero in every document
+                let zero_count = |i: usize| count - nonzero_counts.get(i).map(|f| f.0).unwrap_or(0.0);
                 let tfidf = doc
-                    .iter()
-                    .zip(freq_vector.iter())
-                    .map(|(&doc_count, &freq)| {
-                        if doc_count == 0.0 || freq == 0 {
-                            0.0
-                        } else {
-                            let tf = doc_count;
-                            let idf = (frequencies.count as f64 / freq as f64).ln();
-                            tf * idf
-                        }
+                    .nonzero_entries()
+                    .into_iter()
+                    .filter(|(i, _)| zero_count(*i) != 0.0)
+                    .map(|(i, doc_count)| {
+                        let tf = doc_count;
+                        let idf = (count / zero_count(i)).ln();
+                        (i, tf * idf)
                     })
                     .collect();
 
-                (id, RowValue::Vec(tfidf))
+                (id, RowValue::sparse(len, tfidf))
             })
     }
+
+    // keeps every position where it is
+    fn output_width(&self, input_width: Option<Collection<G, usize>>) -> Option<Collection<G, usize>> {
+        input_width
+    }
+
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        self.frequencies.as_ref().map(|f| f.map(|((), params)| ParamRecord::Vector(params)))
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        self.frequencies = Some(params.flat_map(|record| match record {
+            ParamRecord::Vector(params) => Some(((), params)),
+            _ => None,
+        }));
+    }
+
+    // the column prefix of the CountVectorizer names is replaced, e.g. text:berlin becomes tfidf:berlin
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        input_names.map(|(pos, name)| {
+            let token = name.split_once(':').map(|(_column, token)| token).unwrap_or(&name);
+            (pos, format!("tfidf:{}", token))
+        })
+    }
 }
 
 
@@ -91,25 +136,18 @@ where G::Timestamp: Lattice+Ord {
 struct DocumentFrequencyAggregate {
     frequencies: Option<Vec<isize>>,
     count: isize,
-    round_to: Option<i32>,
 }
 
 impl DocumentFrequencyAggregate {
-    fn new(mut document: Vec<isize>, multiplicity: isize, round_to: Option<i32>) -> Self {
+    fn new(mut document: Vec<isize>, multiplicity: isize) -> Self {
         for value in &mut document {
             *value *= multiplicity;
         }
-        Self { frequencies: Some(document), count: multiplicity, round_to}
+        Self { frequencies: Some(document), count: multiplicity}
     }
 
     fn get_frequencies(&self) -> Option<Vec<isize>> {
-        match self.frequencies {
-            None => None,
-            Some(ref v) => match self.round_to {
-                Some(n) => Some(round_to_decimal(v, n)),
-                None => Some(v.clone())
-            }
-        }
+        self.frequencies.clone()
     }
 
     fn get_count(&self) -> isize {
@@ -117,11 +155,6 @@ impl DocumentFrequencyAggregate {
     }
 }
 
-fn round_to_decimal(vec : &Vec<isize>, n: i32) -> Vec<isize> {
-    let factor = 10f64.powi(n);
-    vec.iter().map(|&x| ((x as f64 /factor).round() * factor) as isize).collect::<Vec<isize>>()
-}
-
 impl IsZero for DocumentFrequencyAggregate {
     fn is_zero(&self) -> bool {
         self.count == 0
@@ -143,17 +176,13 @@ impl Semigroup for DocumentFrequencyAggregate {
             }
             _ => {}
         }
-        self.round_to = match self.round_to {
-            None => other.round_to,
-            Some(_) => self.round_to,
-        };
         self.count += other.count;
     }
 }
 
 impl Monoid for DocumentFrequencyAggregate {
     fn zero() -> Self {
-        Self { frequencies: None, count: 0, round_to: None}
+        Self { frequencies: None, count: 0}
     }
 }
 
@@ -172,3 +201,42 @@ impl Abelian for DocumentFrequencyAggregate {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use super::*;
+    #[test]
+    fn feature_names_replace_the_column_prefix() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut names = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let enc = TfidfTransformer::new();
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+
+                enc.feature_names(&names.to_collection(scope))
+                    .inspect(move |(name, _, _)| output_clone.lock().unwrap().push(name.clone()))
+                    .probe()
+            });
+
+            names.advance_to(0);
+            names.insert((0, "text:berlin".to_string()));
+            names.insert((1, "text:a:b".to_string()));
+            names.insert((2, "count".to_string()));
+            names.advance_to(1);
+            names.flush();
+            worker.step_while(|| probe.less_than(names.time()));
+
+            let mut output = output.lock().unwrap();
+            output.sort();
+            assert_eq!(&*output, &vec![
+                (0, "tfidf:berlin".to_string()),
+                (1, "tfidf:a:b".to_string()),
+                (2, "tfidf:count".to_string()),
+            ], "Feature names are incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+}
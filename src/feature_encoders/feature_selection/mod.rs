@@ -0,0 +1,4 @@
+pub mod variance_threshold;
+pub mod select_k_best;
+pub mod proxy_detector;
+mod utils;
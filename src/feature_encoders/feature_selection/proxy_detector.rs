@@ -0,0 +1,223 @@
+use differential_dataflow::Collection;
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::{Count, Reduce, Threshold};
+use timely::dataflow::Scope;
+use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::feature_encoders::feature_selection::select_k_best::{ClassMomentsAggregate, ContingencyAggregate};
+use crate::feature_encoders::fitted_params::ParamRecord;
+use crate::operators::broadcast_join::broadcast_join;
+use crate::operators::correlation_matrix::correlations;
+use crate::types::row::Row;
+use crate::types::row_value::RowValue;
+use crate::types::safe_f64::SafeF64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnKind {
+    Numerical,
+    Categorical,
+}
+
+// Keeps the association between every candidate column and the protected attributes up to date:
+// |pearson correlation| for two numerical columns, the correlation ratio for a numerical and a
+// categorical column and Cramér's V for two categorical columns.
+// A candidate is a proxy if its association with any protected attribute is above the threshold.
+pub struct ProxyDetector {
+    protected: Vec<(usize, ColumnKind)>,
+    candidates: Vec<(usize, ColumnKind)>,
+    threshold: f64,
+}
+
+impl ProxyDetector {
+    pub fn new(protected: Vec<(usize, ColumnKind)>, candidates: Vec<(usize, ColumnKind)>, threshold: f64) -> Self {
+        Self{protected, candidates, threshold}
+    }
+
+    // (candidate column, is proxy) for every candidate column
+    pub fn detect<G: Scope>(&self, data: &Collection<G, (usize, Row)>) -> Collection<G, (usize, bool)>
+    where G::Timestamp: Lattice+Ord {
+        self.proxies(&self.associations(data))
+    }
+
+    // ((candidate column, protected column), association) for every pair, e.g. to log or monitor them
+    pub fn associations<G: Scope>(&self, data: &Collection<G, (usize, Row)>) -> Collection<G, ((usize, usize), SafeF64)>
+    where G::Timestamp: Lattice+Ord {
+        let mut numerical_pairs = Vec::new();
+        let mut mixed_pairs = Vec::new(); // (candidate, protected, numerical column, categorical column)
+        let mut categorical_pairs = Vec::new();
+        for &(c, c_kind) in &self.candidates {
+            for &(p, p_kind) in &self.protected {
+                match (c_kind, p_kind) {
+                    (ColumnKind::Numerical, ColumnKind::Numerical) => numerical_pairs.push((c, p)),
+                    (ColumnKind::Numerical, ColumnKind::Categorical) => mixed_pairs.push((c, p, c, p)),
+                    (ColumnKind::Categorical, ColumnKind::Numerical) => mixed_pairs.push((c, p, p, c)),
+                    (ColumnKind::Categorical, ColumnKind::Categorical) => categorical_pairs.push((c, p)),
+                }
+            }
+        }
+
+        let pearson = correlations(data, numerical_pairs)
+            .map(|(pair, r)| (pair, SafeF64(r.0.abs())));
+
+        let correlation_ratios = data
+            .flat_map(move |(ix, row)| mixed_pairs.iter()
+                .map(|&(c, p, num, cat)| ((c, p), (ix, row.values[cat].clone(), SafeF64(row.values[num].get_float()))))
+                .collect::<Vec<_>>())
+            .threshold(|(_pair, (_ix, class, x)), c| ClassMomentsAggregate::new(class, x.0, *c))
+            .map(|(pair, _value)| pair)
+            .count()
+            .map(|(pair, agg)| (pair, SafeF64(agg.correlation_ratio())));
+
+        let cramers_v = data
+            .flat_map(move |(ix, row)| categorical_pairs.iter()
+                .map(|&(c, p)| ((c, p), (ix, row.values[c].clone(), row.values[p].clone())))
+                .collect::<Vec<_>>())
+            .threshold(|(_pair, (_ix, a, b)), c| ContingencyAggregate::new(a, b, *c))
+            .map(|(pair, _value)| pair)
+            .count()
+            .map(|(pair, agg)| (pair, SafeF64(agg.cramers_v())));
+
+        pearson
+            .concat(&correlation_ratios)
+            .concat(&cramers_v)
+    }
+
+    // (candidate column, is proxy) for the given associations
+    pub fn proxies<G: Scope>(&self, associations: &Collection<G, ((usize, usize), SafeF64)>) -> Collection<G, (usize, bool)>
+    where G::Timestamp: Lattice+Ord {
+        let threshold = self.threshold;
+        associations
+            .map(|((c, p), score)| (c, (p, score)))
+            .reduce(move |_candidate, input, output| {
+                let is_proxy = input.iter().any(|(p_score, _)| (p_score.1).0 > threshold);
+                output.push((is_proxy, 1isize));
+            })
+    }
+}
+
+// ProxyGate passes an encoded column through, unless the detector flagged the column as a proxy,
+// then the column is encoded as an empty vector and disappears from the concatenated output
+pub struct ProxyGate<G: Scope> {
+    column: usize,
+    proxies: Collection<G, (usize, bool)>,
+}
+
+impl<G: Scope> ProxyGate<G> {
+    pub fn new(column: usize, proxies: &Collection<G, (usize, bool)>) -> Self {
+        Self{column, proxies: proxies.clone()}
+    }
+}
+
+impl<G: Scope> ColumnEncoder<G> for ProxyGate<G>
+where G::Timestamp: Lattice+Ord {
+    fn fit(&mut self, _data: &Collection<G, (usize, RowValue)>) {
+
+    }
+
+    fn transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
+        let column = self.column;
+        broadcast_join(&data.map(move |x| (column, x)), &self.proxies)
+            .map(|(_column, ((ix, val), is_proxy))| {
+                (ix, if is_proxy { RowValue::Vec(vec![]) } else { val })
+            })
+    }
+
+    // the flag of the gated column
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        let column = self.column;
+        Some(self.proxies
+            .filter(move |(c, _is_proxy)| *c == column)
+            .map(|(c, is_proxy)| ParamRecord::Flag(c, is_proxy)))
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        self.proxies = params.flat_map(|record| match record {
+            ParamRecord::Flag(column, is_proxy) => Some((column, is_proxy)),
+            _ => None,
+        });
+    }
+
+    // a flagged column has width 0
+    fn output_width(&self, input_width: Option<Collection<G, usize>>) -> Option<Collection<G, usize>> {
+        let column = self.column;
+        input_width.map(|width| broadcast_join(&width.map(move |width| (column, width)), &self.proxies)
+            .map(|(_column, (width, is_proxy))| if is_proxy { 0 } else { width }))
+    }
+
+    // a flagged column has no output positions, so it has no names either
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        let column = self.column;
+        broadcast_join(&input_names.map(move |x| (column, x)), &self.proxies)
+            .flat_map(|(_column, (pos_name, is_proxy))| if is_proxy { None } else { Some(pos_name) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use super::*;
+
+    // (sex, a categorical copy of it, a number)
+    fn row(ix: usize, number: f64) -> Row {
+        let sex = if ix % 2 == 0 { "m" } else { "f" };
+        let copy = if ix % 2 == 0 { "x" } else { "y" };
+        Row::with_row_values(vec![RowValue::Text(sex.to_string()), RowValue::Text(copy.to_string()), RowValue::Float(number)])
+    }
+
+    #[test]
+    fn proxies_follow_the_data() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+        let result = timely::execute(timely::Config::process(2), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::clone(&output_clone);
+            let probe = worker.dataflow(|scope| {
+                let detector = ProxyDetector::new(
+                    vec![(0, ColumnKind::Categorical)],
+                    vec![(1, ColumnKind::Categorical), (2, ColumnKind::Numerical)],
+                    0.5
+                );
+                detector.detect(&input.to_collection(scope))
+                    .inspect(move |((column, is_proxy), time, diff)| output.lock().unwrap().push((*time, *column, *is_proxy, *diff)))
+                    .probe()
+            });
+
+            // the number has the same distribution for both values of the protected column
+            input.advance_to(0);
+            if worker.index() == 0 {
+                for ix in 0 .. 8 {
+                    input.insert((ix, row(ix, (ix / 2) as f64)));
+                }
+            }
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // now the number gives the protected column away
+            if worker.index() == 0 {
+                for ix in 0 .. 8 {
+                    input.remove((ix, row(ix, (ix / 2) as f64)));
+                    input.insert((ix, row(ix, (ix % 2) as f64 * 10.0)));
+                }
+            }
+            input.advance_to(2);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+
+        // the flags at every time
+        let mut flags: BTreeMap<(usize, usize, bool), isize> = BTreeMap::new();
+        for (time, column, is_proxy, diff) in output.lock().unwrap().iter() {
+            for t in *time .. 2 {
+                *flags.entry((t, *column, *is_proxy)).or_insert(0) += diff;
+            }
+        }
+        let flags: Vec<(usize, usize, bool)> = flags.into_iter()
+            .filter(|(_, count)| *count != 0)
+            .map(|(flag, _)| flag)
+            .collect();
+        assert_eq!(flags, vec![(0, 1, true), (0, 2, false), (1, 1, true), (1, 2, true)], "Proxy flags are incorrect");
+    }
+}
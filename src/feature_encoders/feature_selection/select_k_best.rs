@@ -0,0 +1,503 @@
+use std::collections::HashMap;
+use differential_dataflow::Collection;
+use differential_dataflow::difference::{Abelian, IsZero, Monoid, Semigroup};
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::{Count, Join, Reduce, Threshold};
+use serde::{Deserialize, Serialize};
+use timely::dataflow::Scope;
+use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::feature_encoders::feature_selection::utils::{apply_selection, nonzero_positions, select_names};
+use crate::feature_encoders::fitted_params::{export_selection, import_selection, ParamRecord};
+use crate::operators::broadcast_join::broadcast_join;
+use crate::operators::parallel_fit::{by_row_id, partitioned_aggregate};
+use crate::types::row_value::RowValue;
+use crate::types::safe_f64::SafeF64;
+use crate::types::safe_hash_map::SafeHashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScoreFunction {
+    Chi2,
+    FClassif,
+    MutualInfo,
+}
+
+// class -> (sum, sum of squares, count) of a single feature
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) struct ClassMomentsAggregate {
+    moments: SafeHashMap<RowValue, (SafeF64, SafeF64, isize)>,
+}
+
+impl ClassMomentsAggregate {
+    pub(crate) fn new(class: &RowValue, value: f64, multiplicity: isize) -> Self {
+        let mut new = Self::zero();
+        let m = multiplicity as f64;
+        new.moments.insert(class.clone(), (SafeF64(value * m), SafeF64(value * value * m), multiplicity));
+        new
+    }
+
+    // the moments of a feature that is 0.0 in every row without an entry, rows holds the row count of every class
+    pub(crate) fn with_zeros(&self, rows: &Self) -> Self {
+        let mut filled = rows.clone();
+        for (class, (s, sq, _n)) in self.moments.iter() {
+            if let Some(moments) = filled.moments.0.get_mut(class) {
+                moments.0 = *s;
+                moments.1 = *sq;
+            }
+        }
+        filled
+    }
+
+    fn totals(&self) -> (f64, f64, f64) {
+        self.moments.iter().fold((0.0, 0.0, 0.0), |(s, sq, n), (_, (s_c, sq_c, n_c))| {
+            (s + s_c.0, sq + sq_c.0, n + *n_c as f64)
+        })
+    }
+
+    // chi2 statistic of the class-conditional feature sums (expects non-negative features)
+    pub(crate) fn chi2(&self) -> f64 {
+        let (total, _, n) = self.totals();
+        self.moments.iter().map(|(_, (s_c, _, n_c))| {
+            let expected = *n_c as f64 / n * total;
+            if expected == 0.0 { 0.0 } else { (s_c.0 - expected).powi(2) / expected }
+        }).sum()
+    }
+
+    // (sum of squares between classes, total sum of squares)
+    fn sum_of_squares(&self) -> (f64, f64) {
+        let (total, total_sq, n) = self.totals();
+        let correction = total * total / n;
+        let between: f64 = self.moments.iter()
+            .map(|(_, (s_c, _, n_c))| s_c.0 * s_c.0 / *n_c as f64)
+            .sum::<f64>() - correction;
+        (between, total_sq - correction)
+    }
+
+    // one-way ANOVA F-value
+    pub(crate) fn f_value(&self) -> f64 {
+        let k = self.moments.len() as f64;
+        let (_, _, n) = self.totals();
+        if k < 2.0 || n <= k {
+            return 0.0;
+        }
+        let (between, total) = self.sum_of_squares();
+        let within = total - between;
+        if within <= 0.0 {
+            return if between > 0.0 { f64::INFINITY } else { 0.0 };
+        }
+        (between / (k - 1.0)) / (within / (n - k))
+    }
+
+    // correlation ratio (eta) between the feature and the class
+    pub(crate) fn correlation_ratio(&self) -> f64 {
+        let (between, total) = self.sum_of_squares();
+        if total <= 0.0 { 0.0 } else { (between / total).max(0.0).sqrt() }
+    }
+}
+
+impl IsZero for ClassMomentsAggregate {
+    fn is_zero(&self) -> bool {
+        self.moments.len() == 0
+    }
+}
+
+impl Semigroup for ClassMomentsAggregate {
+    fn plus_equals(&mut self, other: &Self) {
+        for (class, (s, sq, n)) in other.moments.iter() {
+            let (s_old, sq_old, n_old) = *self.moments.get(class)
+                .unwrap_or(&(SafeF64(0.0), SafeF64(0.0), 0));
+            if n_old + n == 0 {
+                self.moments.remove(class);
+            } else {
+                self.moments.insert(class.clone(), (SafeF64(s_old.0 + s.0), SafeF64(sq_old.0 + sq.0), n_old + n));
+            }
+        }
+    }
+}
+
+impl Monoid for ClassMomentsAggregate {
+    fn zero() -> Self {
+        Self { moments: SafeHashMap::new() }
+    }
+}
+
+impl Abelian for ClassMomentsAggregate {
+    fn negate(&mut self) {
+        for (_, (s, sq, n)) in self.moments.0.iter_mut() {
+            *s = SafeF64(-s.0);
+            *sq = SafeF64(-sq.0);
+            *n = -*n;
+        }
+    }
+}
+
+// (left value, right value) -> count
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) struct ContingencyAggregate {
+    counts: SafeHashMap<(RowValue, RowValue), isize>,
+}
+
+impl ContingencyAggregate {
+    pub(crate) fn new(left: &RowValue, right: &RowValue, multiplicity: isize) -> Self {
+        let mut new = Self::zero();
+        new.counts.insert((left.clone(), right.clone()), multiplicity);
+        new
+    }
+
+    // the table of a feature that is 0 in every row without an entry, rows holds the row count of every class
+    // as (0, class) counts
+    pub(crate) fn with_zeros(&self, rows: &Self) -> Self {
+        let mut filled = rows.clone();
+        filled.plus_equals(self);
+        for ((_value, class), c) in self.counts.iter() {
+            filled.plus_equals(&Self::new(&RowValue::Integer(0), class, -c));
+        }
+        filled
+    }
+
+    fn marginals(&self) -> (HashMap<&RowValue, f64>, HashMap<&RowValue, f64>, f64) {
+        let mut left = HashMap::new();
+        let mut right = HashMap::new();
+        let mut n = 0.0;
+        for ((l, r), c) in self.counts.iter() {
+            *left.entry(l).or_insert(0.0) += *c as f64;
+            *right.entry(r).or_insert(0.0) += *c as f64;
+            n += *c as f64;
+        }
+        (left, right, n)
+    }
+
+    // mutual information (in nats) between the two discrete variables
+    pub(crate) fn mutual_information(&self) -> f64 {
+        let (left, right, n) = self.marginals();
+        self.counts.iter().map(|((l, r), c)| {
+            let c = *c as f64;
+            c / n * (c * n / (left[l] * right[r])).ln()
+        }).sum()
+    }
+
+    // Cramér's V, the chi2 statistic of the contingency table normalised to [0, 1]
+    pub(crate) fn cramers_v(&self) -> f64 {
+        let (left, right, n) = self.marginals();
+        let dof = (left.len().min(right.len()) as f64) - 1.0;
+        if dof <= 0.0 {
+            return 0.0;
+        }
+        let phi2: f64 = self.counts.iter()
+            .map(|((l, r), c)| (*c as f64).powi(2) / (left[l] * right[r]))
+            .sum::<f64>() - 1.0;
+        (phi2.max(0.0) / dof).sqrt()
+    }
+}
+
+impl IsZero for ContingencyAggregate {
+    fn is_zero(&self) -> bool {
+        self.counts.len() == 0
+    }
+}
+
+impl Semigroup for ContingencyAggregate {
+    fn plus_equals(&mut self, other: &Self) {
+        for (key, c) in other.counts.iter() {
+            let new_count = *self.counts.get(key).unwrap_or(&0) + c;
+            if new_count == 0 {
+                self.counts.remove(key);
+            } else {
+                self.counts.insert(key.clone(), new_count);
+            }
+        }
+    }
+}
+
+impl Monoid for ContingencyAggregate {
+    fn zero() -> Self {
+        Self { counts: SafeHashMap::new() }
+    }
+}
+
+impl Abelian for ContingencyAggregate {
+    fn negate(&mut self) {
+        for (_, c) in self.counts.0.iter_mut() {
+            *c = -*c;
+        }
+    }
+}
+
+// the feature value as a category for the mutual information, None for a non-integer value (the feature has to be
+// binned first, e.g. with the KBinsDiscretizer)
+fn discrete(x: f64) -> Option<RowValue> {
+    if x.fract() != 0.0 {
+        None
+    } else {
+        Some(RowValue::Integer(x as i64))
+    }
+}
+
+// SelectKBest = sklearn's SelectKBest
+// scores every vector position against the labels (row id -> class) and keeps the k best positions,
+// mutual information needs discrete (integer valued) features, positions with other values are left out of
+// the ranking and reported by rejected instead of stopping the stream
+pub struct SelectKBest<G: Scope> {
+    labels: Collection<G, (usize, RowValue)>,
+    score_func: ScoreFunction,
+    k: usize,
+    selected: Option<Collection<G, ((), Vec<usize>)>>,
+    rejected: Option<Collection<G, usize>>,
+}
+
+impl<G: Scope> SelectKBest<G> {
+    pub fn new(labels: &Collection<G, (usize, RowValue)>, score_func: ScoreFunction, k: usize) -> Self {
+        Self{labels: labels.clone(), score_func, k, selected:None, rejected:None}
+    }
+
+    // the positions that can't be scored, a position is rejected while one of its values is not discrete
+    // (only with ScoreFunction::MutualInfo)
+    pub fn rejected(&self) -> Collection<G, usize> {
+        match &self.rejected {
+            None => panic!("called rejected before fit"),
+            Some(r) => r.clone()
+        }
+    }
+}
+
+impl<G: Scope> ColumnEncoder<G> for SelectKBest<G>
+where G::Timestamp: Lattice+Ord {
+    fn fit(&mut self, data: &Collection<G, (usize, RowValue)>) {
+        let k = self.k;
+        let score_func = self.score_func;
+        let labelled = data.join(&self.labels);
+        // only the non-zero entries are aggregated per position, the zeros are added from the rows of every class
+        let nonzero = labelled
+            .flat_map(|(ix, (value, label))| nonzero_positions(&value).into_iter()
+                .map(move |(pos, x)| (pos, (ix, x, label.clone()))));
+        let class_rows = labelled.map(|(ix, (_value, label))| ((), (ix, label)));
+        let (scores, rejected) = match score_func {
+            ScoreFunction::Chi2 | ScoreFunction::FClassif => {
+                let rows = partitioned_aggregate(&class_rows, by_row_id, |(_ix, label), c| ClassMomentsAggregate::new(label, 0.0, c));
+                let moments = nonzero
+                    .threshold(|(_pos, (_ix, x, label)), c| ClassMomentsAggregate::new(label, x.0, *c))
+                    .map(|(pos, _value)| pos)
+                    .count();
+                let scores = broadcast_join(&moments.map(|(pos, agg)| ((), (pos, agg))), &rows)
+                    .map(move |((), ((pos, agg), rows))| {
+                        let agg = agg.with_zeros(&rows);
+                        let score = if score_func == ScoreFunction::Chi2 { agg.chi2() } else { agg.f_value() };
+                        (pos, SafeF64(score))
+                    });
+                (scores, nonzero.flat_map(|_| None::<usize>))
+            }
+            ScoreFunction::MutualInfo => {
+                let rows = partitioned_aggregate(&class_rows, by_row_id, |(_ix, label), c| ContingencyAggregate::new(&RowValue::Integer(0), label, c));
+                let tables = nonzero
+                    .flat_map(|(pos, (ix, x, label))| discrete(x.0).map(|value| (pos, (ix, value, label))))
+                    .threshold(|(_pos, (_ix, value, label)), c| ContingencyAggregate::new(value, label, *c))
+                    .map(|(pos, _value)| pos)
+                    .count();
+                let rejected = nonzero
+                    .filter(|(_pos, (_ix, x, _label))| discrete(x.0).is_none())
+                    .map(|(pos, _value)| pos)
+                    .distinct();
+                let scores = broadcast_join(&tables.map(|(pos, agg)| ((), (pos, agg))), &rows)
+                    .map(|((), ((pos, agg), rows))| (pos, SafeF64(agg.with_zeros(&rows).mutual_information())))
+                    .antijoin(&rejected);
+                (scores, rejected)
+            }
+        };
+        self.rejected = Some(rejected);
+        // the selected positions only change if the top k changes, not on every score update
+        self.selected = Some(scores
+            .map(|(pos, score)| ((), (pos, score)))
+            .reduce(move |_key, input, output| {
+                let mut ranked: Vec<(usize, SafeF64)> = input.iter().map(|(pos_score, _)| **pos_score).collect();
+                ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+                let mut selected: Vec<usize> = ranked.into_iter().take(k).map(|(pos, _)| pos).collect();
+                selected.sort();
+                output.push((selected, 1isize));
+            }));
+    }
+
+    fn transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
+        let selected = match &self.selected {
+            None => panic!("called transform before fit"),
+            Some(s) => s
+        };
+        apply_selection(data, selected)
+    }
+
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        self.selected.as_ref().map(export_selection)
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        self.selected = Some(import_selection(params));
+    }
+
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        let selected = match &self.selected {
+            None => panic!("called feature_names before fit"),
+            Some(s) => s
+        };
+        select_names(input_names, selected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use super::*;
+
+    fn moments(features: &[f64], classes: &[i64]) -> ClassMomentsAggregate {
+        let mut agg = ClassMomentsAggregate::zero();
+        for (x, class) in features.iter().zip(classes.iter()) {
+            agg.plus_equals(&ClassMomentsAggregate::new(&RowValue::Integer(*class), *x, 1));
+        }
+        agg
+    }
+
+    fn contingency(left: &[i64], right: &[i64]) -> ContingencyAggregate {
+        let mut agg = ContingencyAggregate::zero();
+        for (l, r) in left.iter().zip(right.iter()) {
+            agg.plus_equals(&ContingencyAggregate::new(&RowValue::Integer(*l), &RowValue::Integer(*r), 1));
+        }
+        agg
+    }
+
+    #[test]
+    fn scores_match_known_values() {
+        let agg = moments(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[0, 0, 0, 1, 1, 1]);
+        // the values of sklearn's chi2 and f_classif
+        assert!((agg.chi2() - 3.857142857142857).abs() < 1e-9, "chi2 is incorrect");
+        assert!((agg.f_value() - 13.5).abs() < 1e-9, "f_value is incorrect");
+        assert!((agg.correlation_ratio() - (13.5f64 / 17.5).sqrt()).abs() < 1e-9, "correlation_ratio is incorrect");
+
+        let agg = contingency(&[0, 0, 0, 1, 1, 1], &[0, 0, 1, 1, 1, 1]);
+        assert!((agg.cramers_v() - 0.5f64.sqrt()).abs() < 1e-9, "cramers_v is incorrect");
+        assert!((agg.mutual_information() - 0.3182570841474064).abs() < 1e-9, "mutual_information is incorrect");
+    }
+
+    #[test]
+    fn retractions_restore_the_scores() {
+        let mut agg = moments(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[0, 0, 0, 1, 1, 1]);
+        let mut retracted = moments(&[9.0, 7.0], &[0, 2]);
+        agg.plus_equals(&retracted);
+        retracted.negate();
+        agg.plus_equals(&retracted);
+        assert_eq!(agg, moments(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[0, 0, 0, 1, 1, 1]), "class moments are incorrect");
+        assert!((agg.f_value() - 13.5).abs() < 1e-9, "f_value is incorrect");
+
+        let mut agg = contingency(&[0, 0, 0, 1, 1, 1], &[0, 0, 1, 1, 1, 1]);
+        let mut retracted = contingency(&[2, 0], &[0, 3]);
+        agg.plus_equals(&retracted);
+        retracted.negate();
+        agg.plus_equals(&retracted);
+        assert!((agg.cramers_v() - 0.5f64.sqrt()).abs() < 1e-9, "cramers_v is incorrect");
+    }
+
+    #[test]
+    fn missing_entries_count_as_zero() {
+        // the feature 1, 0, 0, 4, 5, 0 with only its non-zero entries aggregated
+        let classes = [0, 0, 0, 1, 1, 1];
+        let agg = moments(&[1.0, 4.0, 5.0], &[0, 1, 1]).with_zeros(&moments(&[0.0; 6], &classes));
+        assert_eq!(agg, moments(&[1.0, 0.0, 0.0, 4.0, 5.0, 0.0], &classes), "class moments are incorrect");
+
+        let agg = contingency(&[1, 4, 5], &[0, 1, 1]).with_zeros(&contingency(&[0; 6], &classes));
+        let dense = contingency(&[1, 0, 0, 4, 5, 0], &classes);
+        assert!((agg.mutual_information() - dense.mutual_information()).abs() < 1e-9, "mutual_information is incorrect");
+    }
+
+    #[test]
+    fn selection_follows_retracted_rows() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let mut labels = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = SelectKBest::new(&labels.to_collection(scope), ScoreFunction::FClassif, 1);
+                enc.fit(&input_df);
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+
+                enc.export_params().unwrap()
+                    .inspect(move |(record, time, diff)| {
+                        output_clone.lock().unwrap().push((*time, record.clone(), *diff));
+                    })
+                    .probe()
+            });
+
+            // position 0 separates the classes, position 1 doesn't
+            let informative = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+            let noise = [1.0, 3.0, 2.0, 2.0, 3.0, 1.0];
+            input.advance_to(0);
+            labels.advance_to(0);
+            for ix in 0 .. 6 {
+                input.insert((ix, RowValue::Vec(vec![informative[ix], noise[ix]])));
+                labels.insert((ix, RowValue::Integer((ix / 3) as i64)));
+            }
+            input.advance_to(1);
+            labels.advance_to(1);
+            input.flush();
+            labels.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // the rows are retracted and come back with the positions swapped
+            for ix in 0 .. 6 {
+                input.remove((ix, RowValue::Vec(vec![informative[ix], noise[ix]])));
+                input.insert((ix, RowValue::Vec(vec![noise[ix], informative[ix]])));
+            }
+            input.advance_to(2);
+            labels.advance_to(2);
+            input.flush();
+            labels.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            let mut output = output.lock().unwrap();
+            output.sort();
+            assert_eq!(&*output, &vec![
+                (0, ParamRecord::Selection(vec![0]), 1),
+                (1, ParamRecord::Selection(vec![0]), -1),
+                (1, ParamRecord::Selection(vec![1]), 1),
+            ], "Selected positions are incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+
+    #[test]
+    fn mutual_information_rejects_continuous_positions() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let mut labels = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let rejected = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = SelectKBest::new(&labels.to_collection(scope), ScoreFunction::MutualInfo, 2);
+                enc.fit(&input_df);
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+                let rejected_clone = Arc::clone(&rejected);
+
+                enc.rejected()
+                    .inspect(move |(pos, _, diff)| rejected_clone.lock().unwrap().push((*pos, *diff)));
+                enc.export_params().unwrap()
+                    .inspect(move |(record, _, diff)| output_clone.lock().unwrap().push((record.clone(), *diff)))
+                    .probe()
+            });
+
+            // position 1 holds fractions and can't be scored, the stream goes on without it
+            input.advance_to(0);
+            labels.advance_to(0);
+            for ix in 0 .. 6 {
+                input.insert((ix, RowValue::Vec(vec![(ix / 3) as f64, ix as f64 / 4.0])));
+                labels.insert((ix, RowValue::Integer((ix / 3) as i64)));
+            }
+            input.advance_to(1);
+            labels.advance_to(1);
+            input.flush();
+            labels.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            assert_eq!(&*rejected.lock().unwrap(), &vec![(1, 1)], "Rejected positions are incorrect");
+            assert_eq!(&*output.lock().unwrap(), &vec![(ParamRecord::Selection(vec![0]), 1)], "Selected positions are incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+}
@@ -0,0 +1,63 @@
+use differential_dataflow::Collection;
+use differential_dataflow::lattice::Lattice;
+use timely::dataflow::Scope;
+use crate::operators::broadcast_join::broadcast_join;
+use crate::operators::parallel_fit::{by_row_id, partitioned_aggregate};
+use crate::types::row_value::RowValue;
+use crate::types::safe_f64::SafeF64;
+
+// splits a row value into its non-zero (position, value) pairs, scalars are treated as vectors of length one.
+// the fits only aggregate these and add the zeros from the row count (see row_count), so a row counts as 0.0 at every
+// position it doesn't reach, e.g. a one-hot row that ends at its position
+pub(crate) fn nonzero_positions(value: &RowValue) -> Vec<(usize, SafeF64)> {
+    match value {
+        RowValue::Vec(_) | RowValue::Sparse { .. } | RowValue::Integer(_) | RowValue::Float(_) =>
+            value.nonzero_entries().into_iter().map(|(i, x)| (i, SafeF64(x))).collect(),
+        a => panic!("feature selection called on non-numeric row value [{:?}]", a),
+    }
+}
+
+// the number of rows, counted per partition first like the other fitted statistics
+pub(crate) fn row_count<G: Scope>(data: &Collection<G, (usize, RowValue)>) -> Collection<G, ((), isize)>
+where G::Timestamp: Lattice+Ord {
+    partitioned_aggregate(&data.map(|(ix, _value)| ((), (ix, ()))), by_row_id, |_row, c| c)
+}
+
+// keeps only the selected positions (sorted ascending) of a row value, positions the value doesn't have
+// (e.g. a row that is shorter than the widest row the selection was fitted on) are 0.0
+pub(crate) fn project(value: &RowValue, selected: &[usize]) -> RowValue {
+    match value {
+        RowValue::Vec(v) => RowValue::Vec(selected.iter().map(|&i| v.get(i).copied().unwrap_or(0.0)).collect()),
+        RowValue::Integer(_) | RowValue::Float(_) => project(&RowValue::Vec(vec![value.get_float()]), selected),
+        RowValue::Sparse { .. } => project(&value.to_dense(), selected),
+        a => panic!("feature selection called on non-numeric row value [{:?}]", a),
+    }
+}
+
+// re-projects every row whenever the selected index set changes
+pub(crate) fn apply_selection<G: Scope>(data: &Collection<G, (usize, RowValue)>, selected: &Collection<G, ((), Vec<usize>)>) -> Collection<G, (usize, RowValue)>
+where G::Timestamp: Lattice+Ord {
+    broadcast_join(&data.map(|x| ((), x)), selected)
+        .map(|(_, ((ix, val), selected))| (ix, project(&val, &selected)))
+}
+
+// the names of the selected positions, renumbered like project renumbers the positions
+pub(crate) fn select_names<G: Scope>(input_names: &Collection<G, (usize, String)>, selected: &Collection<G, ((), Vec<usize>)>) -> Collection<G, (usize, String)>
+where G::Timestamp: Lattice+Ord {
+    broadcast_join(&input_names.map(|x| ((), x)), selected)
+        .flat_map(|(_, ((pos, name), selected))| {
+            selected.iter().position(|&i| i == pos).map(|new_pos| (new_pos, name))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn project_fills_missing_positions_with_zero() {
+        assert_eq!(project(&RowValue::Vec(vec![1.0, 2.0]), &[1, 3]), RowValue::Vec(vec![2.0, 0.0]));
+        assert_eq!(project(&RowValue::sparse(2, vec![(0, 1.0)]), &[0, 2]), RowValue::Vec(vec![1.0, 0.0]));
+        assert_eq!(project(&RowValue::Float(5.0), &[0]), RowValue::Vec(vec![5.0]));
+        assert_eq!(project(&RowValue::Float(5.0), &[]), RowValue::Vec(vec![]));
+    }
+}
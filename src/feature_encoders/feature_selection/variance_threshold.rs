@@ -0,0 +1,172 @@
+use differential_dataflow::Collection;
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::{Count, Reduce, Threshold};
+use timely::dataflow::Scope;
+use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::feature_encoders::feature_selection::utils::{apply_selection, nonzero_positions, row_count, select_names};
+use crate::feature_encoders::fitted_params::{export_selection, import_selection, ParamRecord};
+use crate::feature_encoders::standard_scaler::VarianceAggregate;
+use crate::operators::broadcast_join::broadcast_join;
+use crate::types::row_value::RowValue;
+
+// variances maintained incrementally pick up rounding errors, e.g. a column that is constant after retractions
+// can have a variance of 1e-17, so a variance only counts as above the threshold if it exceeds it by more than this
+const VARIANCE_EPSILON: f64 = 1e-12;
+
+// VarianceThreshold = sklearn's VarianceThreshold
+// keeps the vector positions whose variance is strictly above the threshold, a scalar column whose
+// variance drops below the threshold is turned into an empty vector and vanishes from the concatenated output
+pub struct VarianceThreshold<G: Scope> {
+    selected: Option<Collection<G, ((), Vec<usize>)>>,
+    threshold: f64,
+}
+
+impl<G: Scope> VarianceThreshold<G> {
+    pub fn new(threshold: f64) -> Self {
+        Self{selected:None, threshold}
+    }
+}
+
+impl<G: Scope> ColumnEncoder<G> for VarianceThreshold<G>
+where G::Timestamp: Lattice+Ord {
+    fn fit(&mut self, data: &Collection<G, (usize, RowValue)>) {
+        let threshold = self.threshold;
+        let nonzero = data
+            .flat_map(|(ix, value)| nonzero_positions(&value).into_iter().map(move |(pos, x)| (pos, (ix, x))))
+            .threshold(|(_pos, (_ix, x)), c| VarianceAggregate::new(x.0, *c))
+            .map(|(pos, _value)| pos)
+            .count();
+        // every row without an entry at a position is a 0.0 there. positions that are 0.0 in every row have no
+        // variance and are never selected
+        let variances = broadcast_join(&nonzero.map(|(pos, agg)| ((), (pos, agg))), &row_count(data))
+            .map(|((), ((pos, agg), n_rows))| ((), (pos, agg.with_zeros(n_rows).get().1)));
+        // a single record holding the selected positions, it only changes if the selection changes
+        self.selected = Some(variances
+            .reduce(move |_key, input, output| {
+                let selected: Vec<usize> = input.iter()
+                    .filter(|(pos_var, _)| (pos_var.1).0 > threshold + VARIANCE_EPSILON)
+                    .map(|(pos_var, _)| pos_var.0)
+                    .collect();
+                output.push((selected, 1isize));
+            }));
+    }
+
+    fn transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
+        let selected = match &self.selected {
+            None => panic!("called transform before fit"),
+            Some(s) => s
+        };
+        apply_selection(data, selected)
+    }
+
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        self.selected.as_ref().map(export_selection)
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        self.selected = Some(import_selection(params));
+    }
+
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        let selected = match &self.selected {
+            None => panic!("called feature_names before fit"),
+            Some(s) => s
+        };
+        select_names(input_names, selected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use crate::feature_encoders::one_hot_encoder::OneHotEncoder;
+    use crate::types::integer_assignment_aggregate::WidthPolicy;
+    use super::*;
+    #[test]
+    fn variance_threshold_drops_constant_positions() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = VarianceThreshold::new(0.0);
+                enc.fit(&input_df);
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+
+                enc.transform(&input_df)
+                    .inspect(move |((ix, x),_,_)| {
+                        let mut out = output_clone.lock().unwrap();
+                        out.push((*ix, x.get_vec().clone()));
+                    })
+                    .probe()
+            });
+
+            input.advance_to(0);
+            for person in 0 .. 10 {
+                input.insert((person, RowValue::Vec(vec![(person % 3) as f64, 1.0, person as f64])));
+            }
+
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // lock the Mutex to access data
+            let mut output = output.lock().unwrap();
+            output.sort_by_key(|(ix, _)| *ix);
+
+            // Check the output
+            assert!(!output.is_empty(), "No output was generated");
+            let expected_values: Vec<(usize, Vec<f64>)> = (0..10)
+                .map(|i| (i, vec![(i % 3) as f64, i as f64])).collect();
+            assert_eq!(&*output, &expected_values, "Transformed output is incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+
+    #[test]
+    fn one_hot_rows_count_as_zero_where_they_end() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                // the one-hot rows end at their position, like inside the ColumnTransformer
+                let mut one_hot = OneHotEncoder::new_with_width_policy(WidthPolicy::Exact);
+                one_hot.emit_cells();
+                one_hot.fit(&input_df);
+                let encoded = one_hot.transform(&input_df);
+                let mut enc = VarianceThreshold::new(0.1);
+                enc.fit(&encoded);
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+
+                enc.transform(&encoded)
+                    .inspect(move |((ix, x), _, _)| {
+                        let mut out = output_clone.lock().unwrap();
+                        out.push((*ix, x.get_vec().clone()));
+                    })
+                    .probe()
+            });
+
+            // the variances of the three positions are 0.21, 0.16 and 0.25
+            input.advance_to(0);
+            for person in 0 .. 10 {
+                let category = if person < 3 { "a" } else if person < 5 { "b" } else { "c" };
+                input.insert((person, RowValue::Text(category.to_string())));
+            }
+
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // the last position is only reached by its own rows, it is kept as their zeros count too
+            let output = output.lock().unwrap();
+            assert_eq!(output.len(), 10, "No output was generated");
+            for (ix, x) in output.iter() {
+                assert_eq!(x.len(), 3, "A position of row {} was dropped", ix);
+                assert_eq!(x.iter().sum::<f64>(), 1.0, "Row {} is not one-hot", ix);
+            }
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+}
@@ -0,0 +1,106 @@
+use differential_dataflow::Collection;
+use differential_dataflow::lattice::Lattice;
+use timely::dataflow::{Scope};
+use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::feature_encoders::fitted_params::{child_params, nest_params, ParamRecord};
+use crate::feature_encoders::multi_column_encoder::{concat_names, concat_vectors};
+use crate::types::row_value::RowValue;
+
+// FeatureUnion = sklearn's FeatureUnion
+// applies several encoders to the same column and concatenates their outputs in config order
+pub struct FeatureUnion<'a, G : Scope> {
+    config: Vec<Box<dyn ColumnEncoder<G> + 'a>>,
+}
+
+impl<'a, G: Scope> FeatureUnion<'a, G>
+where G::Timestamp: Lattice+Ord {
+    // like the ColumnTransformer, the outputs are padded to their output width when they are concatenated
+    pub fn new(mut config: Vec<Box<dyn ColumnEncoder<G> + 'a>>) -> Self<>{
+        config.iter_mut().for_each(|encoder| encoder.emit_cells());
+        Self{config}
+    }
+}
+
+impl<'a, G: Scope> ColumnEncoder<G> for FeatureUnion<'a, G>
+where G::Timestamp: Lattice+Ord {
+    fn fit(&mut self, data: &Collection<G, (usize, RowValue)>) {
+        for encoder in &mut self.config {
+            encoder.fit(data);
+        }
+    }
+
+    fn transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
+        concat_vectors(
+            self.config.iter().map(|encoder| encoder.transform(data)).collect(),
+            self.config.iter().map(|encoder| encoder.output_width(None)).collect(),
+            None
+        )
+    }
+
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        nest_params(self.config.iter().map(|encoder| encoder.export_params()).collect())
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        for (idx, encoder) in self.config.iter_mut().enumerate() {
+            encoder.import_params(&child_params(params, idx));
+        }
+    }
+
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        concat_names(self.config.iter().map(|encoder| encoder.feature_names(input_names)).collect(), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use crate::feature_encoders::passthrough::Passthrough;
+    use crate::feature_encoders::polynomial_features_encoder::PolynomialFeaturesEncoder;
+    use super::*;
+    #[test]
+    fn feature_union_works() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = FeatureUnion::new(vec![
+                    Box::new(Passthrough::new()),
+                    Box::new(PolynomialFeaturesEncoder::new(2, 3))
+                ]);
+                enc.fit(&input_df);
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+
+                enc.transform(&input_df)
+                    .inspect(move |((ix, x),_,_)| {
+                        let mut out = output_clone.lock().unwrap();
+                        out.push((*ix, x.get_vec().clone()));
+                    })
+                    .probe()
+            });
+
+            input.advance_to(0);
+            for person in 0 .. 10 {
+                let person_int = person as i64;
+                input.insert((person,RowValue::Integer(person_int)));
+            }
+
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // lock the Mutex to access data
+            let mut output = output.lock().unwrap();
+            output.sort_by_key(|(ix, _)| *ix);
+
+            // Check the output
+            assert!(!output.is_empty(), "No output was generated");
+            let expected_values: Vec<(usize, Vec<f64>)> = (0..10)
+                .map(|i| (i, vec![i as f64, (i * i) as f64, (i * i * i) as f64])).collect();
+            assert_eq!(&*output, &expected_values, "Transformed output is incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+}
@@ -0,0 +1,265 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::hash::Hash;
+use std::rc::Rc;
+use differential_dataflow::{AsCollection, Collection, ExchangeData};
+use differential_dataflow::lattice::Lattice;
+use serde::{Deserialize, Serialize};
+use timely::dataflow::{ProbeHandle, Scope};
+use timely::dataflow::operators::{Exchange, ToStream};
+use timely::progress::Timestamp;
+use timely::worker::AsWorker;
+use crate::types::row_value::RowValue;
+use crate::types::safe_f64::SafeF64;
+use crate::types::safe_hash_map::SafeHashMap;
+
+// One record of the fitted state of an encoder, the fitted state of an encoder is a set of records
+// (see ColumnEncoder::export_params and ColumnEncoder::import_params)
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ParamRecord {
+    // (column, (a, b)), e.g. (mean, variance) of the StandardScaler or (min, range) of the MinMaxScaler
+    Pair(usize, (SafeF64, SafeF64)),
+    // value -> encoded value, e.g. the category map of the OrdinalEncoder
+    Mapping(RowValue, RowValue),
+    // (value, position) pairs and the vector width, e.g. the categories of the OneHotEncoder
+    Positions(Vec<(RowValue, usize)>, usize),
+    // the selected vector positions, sorted ascending, e.g. of the VarianceThreshold or SelectKBest
+    Selection(Vec<usize>),
+    // (column, flag), e.g. whether the ProxyDetector flagged a column as a proxy
+    Flag(usize, bool),
+    // a parameter vector, e.g. [document count, document frequencies...] of the TfidfTransformer
+    Vector(Vec<SafeF64>),
+    // the rows of a matrix, e.g. the mean and the components of the Pca
+    Rows(Vec<Vec<SafeF64>>),
+    // a record of the child encoder with the given index in a Pipeline, FeatureUnion or ColumnTransformer
+    Nested(usize, Box<ParamRecord>),
+}
+
+// Snapshot of the fitted parameters of an encoder (usually a whole ColumnTransformer or Pipeline)
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FittedParams {
+    pub records: Vec<ParamRecord>,
+}
+
+impl FittedParams {
+    // non-finite floats are written as null and cannot be read back
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    pub fn to_bincode(&self) -> bincode::Result<Vec<u8>> {
+        bincode::serialize(self)
+    }
+
+    pub fn from_bincode(bytes: &[u8]) -> bincode::Result<Self> {
+        bincode::deserialize(bytes)
+    }
+
+    // the records as a constant collection, pass it to import_params of a fresh (unfitted) encoder.
+    // only worker 0 emits the records, the encoders broadcast or exchange their parameters anyway
+    pub fn to_collection<G: Scope>(&self, scope: &mut G) -> Collection<G, ParamRecord>
+    where G::Timestamp: Lattice+Ord {
+        let records = if scope.index() == 0 { self.records.clone() } else { Vec::new() };
+        records.into_iter()
+            .map(|record| (record, G::Timestamp::minimum(), 1isize))
+            .to_stream(scope)
+            .as_collection()
+    }
+}
+
+// Captures the exported parameters of an encoder outside of the dataflow. The records are gathered on worker 0
+// and only applied once their time is complete, so a snapshot always reflects a completed timestamp.
+pub struct FittedParamsSink<T> {
+    pending: Rc<RefCell<Vec<(T, ParamRecord, isize)>>>,
+    records: BTreeMap<ParamRecord, isize>,
+}
+
+impl<T: Timestamp+Lattice+Ord> FittedParamsSink<T> {
+    pub fn new() -> Self {
+        Self { pending: Rc::new(RefCell::new(Vec::new())), records: BTreeMap::new() }
+    }
+
+    // buffers the changes of the parameters, probe the returned collection and call advance
+    pub fn attach<G: Scope<Timestamp=T>>(&self, params: &Collection<G, ParamRecord>) -> Collection<G, ParamRecord> {
+        let pending = Rc::clone(&self.pending);
+        params.inner
+            .exchange(|_update| 0)
+            .as_collection()
+            .inspect(move |(record, time, diff)| {
+                pending.borrow_mut().push((time.clone(), record.clone(), *diff));
+            })
+    }
+
+    // applies the changes of all completed times
+    pub fn advance(&mut self, probe: &ProbeHandle<T>) {
+        let mut pending = self.pending.borrow_mut();
+        let (complete, rest): (Vec<_>, Vec<_>) = pending.drain(..)
+            .partition(|(time, _, _)| !probe.less_equal(time));
+        *pending = rest;
+        for (_time, record, diff) in complete {
+            let count = self.records.entry(record.clone()).or_insert(0);
+            *count += diff;
+            if *count == 0 {
+                self.records.remove(&record);
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> FittedParams {
+        FittedParams { records: self.records.keys().cloned().collect() }
+    }
+}
+
+// the records of the child encoders, tagged with their index
+pub(crate) fn nest_params<G: Scope>(children: Vec<Option<Collection<G, ParamRecord>>>) -> Option<Collection<G, ParamRecord>>
+where G::Timestamp: Lattice+Ord {
+    children.into_iter().enumerate()
+        .filter_map(|(idx, params)| params.map(|p| p.map(move |record| ParamRecord::Nested(idx, Box::new(record)))))
+        .reduce(|all, p| all.concat(&p))
+}
+
+// the records of the child encoder with the given index
+pub(crate) fn child_params<G: Scope>(params: &Collection<G, ParamRecord>, idx: usize) -> Collection<G, ParamRecord>
+where G::Timestamp: Lattice+Ord {
+    params.flat_map(move |record| match record {
+        ParamRecord::Nested(i, record) if i == idx => Some(*record),
+        _ => None,
+    })
+}
+
+pub(crate) fn export_pairs<G: Scope>(pairs: &Collection<G, (usize, (SafeF64, SafeF64))>) -> Collection<G, ParamRecord>
+where G::Timestamp: Lattice+Ord {
+    pairs.map(|(column, pair)| ParamRecord::Pair(column, pair))
+}
+
+pub(crate) fn import_pairs<G: Scope>(params: &Collection<G, ParamRecord>) -> Collection<G, (usize, (SafeF64, SafeF64))>
+where G::Timestamp: Lattice+Ord {
+    params.flat_map(|record| match record {
+        ParamRecord::Pair(column, pair) => Some((column, pair)),
+        _ => None,
+    })
+}
+
+pub(crate) fn export_selection<G: Scope>(selected: &Collection<G, ((), Vec<usize>)>) -> Collection<G, ParamRecord>
+where G::Timestamp: Lattice+Ord {
+    selected.map(|((), selected)| ParamRecord::Selection(selected))
+}
+
+pub(crate) fn import_selection<G: Scope>(params: &Collection<G, ParamRecord>) -> Collection<G, ((), Vec<usize>)>
+where G::Timestamp: Lattice+Ord {
+    params.flat_map(|record| match record {
+        ParamRecord::Selection(selected) => Some(((), selected)),
+        _ => None,
+    })
+}
+
+// positions as assigned by one_hot_encoder::fit_positions, the keys are stored as RowValues
+pub(crate) fn export_positions<G: Scope, K>(value_positions: &Collection<G, ((), (SafeHashMap<K, usize>, usize))>, to_value: fn(K) -> RowValue) -> Collection<G, ParamRecord>
+where G::Timestamp: Lattice+Ord, K: ExchangeData + Hash {
+    value_positions.map(move |((), (positions, len))| {
+        let mut positions: Vec<(RowValue, usize)> = positions.0.into_iter()
+            .map(|(key, position)| (to_value(key), position))
+            .collect();
+        positions.sort_by_key(|(_, position)| *position);
+        ParamRecord::Positions(positions, len)
+    })
+}
+
+pub(crate) fn import_positions<G: Scope, K>(params: &Collection<G, ParamRecord>, from_value: fn(RowValue) -> K) -> Collection<G, ((), (SafeHashMap<K, usize>, usize))>
+where G::Timestamp: Lattice+Ord, K: ExchangeData + Hash {
+    params.flat_map(move |record| match record {
+        ParamRecord::Positions(positions, len) => {
+            let mut map = SafeHashMap::new();
+            for (value, position) in positions {
+                map.insert(from_value(value), position);
+            }
+            Some(((), (map, len)))
+        }
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use crate::feature_encoders::column_encoder::ColumnEncoder;
+    use crate::feature_encoders::multi_column_encoder::ColumnTransformer;
+    use crate::feature_encoders::ordinal_encoder::OrdinalEncoder;
+    use crate::feature_encoders::row_encoder::RowEncoder;
+    use crate::feature_encoders::standard_scaler::StandardScaler;
+    use crate::types::row::Row;
+    use super::*;
+
+    fn transformer<'a, G: Scope + 'a>() -> ColumnTransformer<'a, G>
+    where G::Timestamp: Lattice+Ord {
+        ColumnTransformer::new(vec![
+            (0, Box::new(StandardScaler::new()) as Box<dyn ColumnEncoder<G> + 'a>),
+            (1, Box::new(OrdinalEncoder::new())),
+        ])
+    }
+
+    #[test]
+    fn imported_params_transform_like_fitted_ones() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let fitted_output = Arc::new(Mutex::new(Vec::new()));
+            let mut sink = FittedParamsSink::new();
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = transformer();
+                enc.fit_rows(&input_df);
+                let output_clone = Arc::clone(&fitted_output); // Clone Arc for use inside closure
+                enc.transform_vectors(&input_df)
+                    .inspect(move |((ix, x), _, _)| output_clone.lock().unwrap().push((*ix, x.get_vec().clone())));
+                sink.attach(&enc.export_params().unwrap()).probe()
+            });
+
+            input.advance_to(0);
+            for ix in 0 .. 4 {
+                input.insert((ix, Row::with_row_values(vec![RowValue::Integer(ix as i64), RowValue::Integer((ix % 2) as i64)])));
+            }
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+            sink.advance(&probe);
+
+            // the snapshot survives both formats
+            let params = sink.snapshot();
+            assert_eq!(FittedParams::from_json(&params.to_json().unwrap()).unwrap(), params);
+            assert_eq!(FittedParams::from_bincode(&params.to_bincode().unwrap()).unwrap(), params);
+
+            // a pre-fitted transformer in a separate dataflow, without fit
+            let mut serving_input = InputSession::new();
+            let imported_output = Arc::new(Mutex::new(Vec::new()));
+            let serving_probe = worker.dataflow(|scope| {
+                let mut enc = transformer();
+                enc.import_params(&params.to_collection(scope));
+                let output_clone = Arc::clone(&imported_output);
+                enc.transform_vectors(&serving_input.to_collection(scope))
+                    .inspect(move |((ix, x), _, _)| output_clone.lock().unwrap().push((*ix, x.get_vec().clone())))
+                    .probe()
+            });
+
+            serving_input.advance_to(0);
+            for ix in 0 .. 4 {
+                serving_input.insert((ix, Row::with_row_values(vec![RowValue::Integer(ix as i64), RowValue::Integer((ix % 2) as i64)])));
+            }
+            serving_input.advance_to(1);
+            serving_input.flush();
+            worker.step_while(|| serving_probe.less_than(serving_input.time()));
+
+            let mut fitted_output = fitted_output.lock().unwrap().clone();
+            let mut imported_output = imported_output.lock().unwrap().clone();
+            fitted_output.sort_by_key(|(ix, _)| *ix);
+            imported_output.sort_by_key(|(ix, _)| *ix);
+            assert!(!imported_output.is_empty(), "No output was generated");
+            assert_eq!(imported_output, fitted_output, "Imported parameters transform differently");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+}
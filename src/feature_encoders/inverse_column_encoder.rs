@@ -0,0 +1,129 @@
+use differential_dataflow::Collection;
+use differential_dataflow::lattice::Lattice;
+use timely::dataflow::{Scope};
+use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::types::row_value::RowValue;
+
+pub trait InverseColumnEncoder<G: Scope>: ColumnEncoder<G>
+where
+    G::Timestamp: Lattice+Ord,
+{
+    /// Maps encoded values back to the original domain using the internally stored metadata,
+    /// a change of the metadata updates the decoded values like it updates the encoded ones
+    fn inverse_transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use crate::feature_encoders::kbins_discretizer::KBinsDiscretizer;
+    use crate::feature_encoders::minmax_scaler::MinMaxScaler;
+    use crate::feature_encoders::one_hot_encoder::OneHotEncoder;
+    use crate::feature_encoders::ordinal_encoder::OrdinalEncoder;
+    use crate::feature_encoders::standard_scaler::StandardScaler;
+    use super::*;
+    #[test]
+    fn inverse_transform_restores_the_input() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let scaled = Arc::new(Mutex::new(Vec::new()));
+            let one_hot = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut scaler_enc = StandardScaler::new();
+                scaler_enc.fit(&input_df);
+                let mut one_hot_enc = OneHotEncoder::new();
+                one_hot_enc.fit(&input_df);
+                let scaled_clone = Arc::clone(&scaled); // Clone Arc for use inside closure
+                let one_hot_clone = Arc::clone(&one_hot);
+
+                scaler_enc.inverse_transform(&scaler_enc.transform(&input_df))
+                    .inspect(move |((ix, x), _, _)| scaled_clone.lock().unwrap().push((*ix, x.get_float())));
+                one_hot_enc.inverse_transform(&one_hot_enc.transform(&input_df))
+                    .inspect(move |((ix, x), _, _)| one_hot_clone.lock().unwrap().push((*ix, x.get_integer())))
+                    .probe()
+            });
+
+            input.advance_to(0);
+            for person in 0 .. 4 {
+                input.insert((person, RowValue::Integer(person as i64)));
+            }
+
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // lock the Mutex to access data
+            let mut scaled = scaled.lock().unwrap();
+            scaled.sort_by_key(|(ix, _)| *ix);
+            let mut one_hot = one_hot.lock().unwrap();
+            one_hot.sort();
+
+            assert_eq!(scaled.len(), 4, "No output was generated");
+            for (ix, x) in scaled.iter() {
+                assert!((x - *ix as f64).abs() < 1e-9, "Inverse scaling is incorrect");
+            }
+            assert_eq!(&*one_hot, &vec![(0, 0), (1, 1), (2, 2), (3, 3)], "Inverse one-hot encoding is incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+
+    #[test]
+    fn inverse_transform_of_minmax_ordinal_and_kbins() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let scaled = Arc::new(Mutex::new(Vec::new()));
+            let ordinal = Arc::new(Mutex::new(Vec::new()));
+            let binned = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut minmax_enc = MinMaxScaler::new();
+                minmax_enc.fit(&input_df);
+                // the last value is unknown to the ordinal encoder
+                let mut ordinal_enc = OrdinalEncoder::new();
+                ordinal_enc.fit(&input_df.filter(|(ix, _)| *ix < 3));
+                let mut kbins_enc = KBinsDiscretizer::new(2);
+                kbins_enc.fit(&input_df);
+                let scaled_clone = Arc::clone(&scaled); // Clone Arc for use inside closure
+                let ordinal_clone = Arc::clone(&ordinal);
+                let binned_clone = Arc::clone(&binned);
+
+                minmax_enc.inverse_transform(&minmax_enc.transform(&input_df))
+                    .inspect(move |((ix, x), _, _)| scaled_clone.lock().unwrap().push((*ix, x.get_float())));
+                ordinal_enc.inverse_transform(&ordinal_enc.transform(&input_df))
+                    .inspect(move |((ix, x), _, _)| ordinal_clone.lock().unwrap().push((*ix, x.get_integer())));
+                kbins_enc.inverse_transform(&kbins_enc.transform(&input_df))
+                    .inspect(move |((ix, x), _, _)| binned_clone.lock().unwrap().push((*ix, x.get_float())))
+                    .probe()
+            });
+
+            input.advance_to(0);
+            for person in 0 .. 4 {
+                input.insert((person, RowValue::Integer(person as i64)));
+            }
+
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            let mut scaled = scaled.lock().unwrap();
+            scaled.sort_by_key(|(ix, _)| *ix);
+            assert_eq!(scaled.len(), 4, "No output was generated");
+            for (ix, x) in scaled.iter() {
+                assert!((x - *ix as f64).abs() < 1e-9, "Inverse min-max scaling is incorrect");
+            }
+
+            // the row with the unknown code -1 has no category and is dropped
+            let mut ordinal = ordinal.lock().unwrap();
+            ordinal.sort();
+            assert_eq!(&*ordinal, &vec![(0, 0), (1, 1), (2, 2)], "Inverse ordinal encoding is incorrect");
+
+            // min 0 and range 3 give the bins [0, 1.5) and [1.5, 3] with the centers 0.75 and 2.25
+            let mut binned = binned.lock().unwrap();
+            binned.sort_by_key(|(ix, _)| *ix);
+            assert_eq!(&*binned, &vec![(0, 0.75), (1, 0.75), (2, 2.25), (3, 2.25)], "Inverse binning is incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+}
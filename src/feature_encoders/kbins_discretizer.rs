@@ -1,31 +1,50 @@
 use differential_dataflow::Collection;
 use differential_dataflow::lattice::Lattice;
-use differential_dataflow::operators::{Join};
 use timely::dataflow::Scope;
 use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::feature_encoders::fitted_params::{export_pairs, import_pairs, ParamRecord};
+use crate::feature_encoders::inverse_column_encoder::InverseColumnEncoder;
 use crate::feature_encoders::minmax_scaler::{get_meta};
+use crate::operators::broadcast_join::broadcast_join;
+use crate::operators::republish::{republish_pairs, RepublishPolicy};
 use crate::types::row_value::RowValue;
 use crate::types::safe_f64::SafeF64;
 
 pub struct KBinsDiscretizer<G: Scope> {
     meta: Option<Collection<G, (usize, (SafeF64, SafeF64))>>,
+    staleness: Option<Collection<G, (usize, usize)>>,
+    republish_policy: RepublishPolicy,
     k: usize,
 }
 
 impl<G: Scope> KBinsDiscretizer<G> {
     pub fn new(k: usize) -> Self{
-        Self{meta:None, k}
+        Self::new_with_republish_policy(k, RepublishPolicy::Always)
+    }
+
+    pub fn new_with_republish_policy(k: usize, republish_policy: RepublishPolicy) -> Self{
+        Self{meta:None, staleness:None, republish_policy, k}
+    }
+
+    // number of changes of (min, range) that the republish policy holds back
+    pub fn staleness(&self) -> Collection<G, (usize, usize)> {
+        match &self.staleness {
+            None => panic!("called staleness before fit"),
+            Some(s) => s.clone()
+        }
     }
 }
 
 impl<G: Scope> ColumnEncoder<G> for KBinsDiscretizer<G>
 where G::Timestamp: Lattice+Ord {
     fn fit(&mut self, data: &Collection<G, (usize, RowValue)>) {
-        let meta = get_meta(&data.map(|x| (1, x)))
+        let (meta, staleness) = republish_pairs(&get_meta(&data.map(|x| (1, x))), self.republish_policy.clone());
+        let meta = meta
             .inspect(|(record, time, change)| {
                 println!("KBins Meta: {:?}, time: {:?}, change: {:?}", record, time, change)
             });
         self.meta = Some(meta);
+        self.staleness = Some(staleness);
     }
 
     fn transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
@@ -34,7 +53,7 @@ where G::Timestamp: Lattice+Ord {
             Some(m) => m
         };
         let tmp_k = self.k; // make tmp_k outlive self.k -> avoid lifetime error
-        data.map(|x| (1, x)).join(meta)
+        broadcast_join(&data.map(|x| (1, x)), meta)
             .map(move |(_key, ((ix, val), (mean, var)))| {
                 let scaled = (val.get_float() - mean.0) / var.0;
                 let mut bin_id = (scaled * (tmp_k as f64)) as isize;
@@ -44,6 +63,35 @@ where G::Timestamp: Lattice+Ord {
                 return (ix, RowValue::Float(bin_id as f64));
             })
     }
+
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        self.meta.as_ref().map(export_pairs)
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        self.meta = Some(import_pairs(params));
+    }
+
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        input_names.map(|(pos, name)| (pos, format!("{}_bin", name)))
+    }
+}
+
+impl<G: Scope> InverseColumnEncoder<G> for KBinsDiscretizer<G>
+where G::Timestamp: Lattice+Ord {
+    // every bin id becomes the center of its bin
+    fn inverse_transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
+        let meta = match &self.meta {
+            None => panic!("called inverse_transform before fit"),
+            Some(m) => m
+        };
+        let k = self.k;
+        broadcast_join(&data.map(|x| (1, x)), meta)
+            .map(move |(_key, ((ix, bin_id), (min, range)))| {
+                let center = min.0 + (bin_id.get_float() + 0.5) * range.0 / (k as f64);
+                (ix, RowValue::Float(center))
+            })
+    }
 }
 
 #[cfg(test)]
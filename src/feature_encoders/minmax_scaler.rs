@@ -2,12 +2,15 @@ use std::cmp::Ordering;
 use differential_dataflow::Collection;
 use differential_dataflow::difference::{Abelian, IsZero, Monoid, Semigroup};
 use differential_dataflow::lattice::Lattice;
-use differential_dataflow::operators::{Threshold, Count};
 use priority_queue::PriorityQueue;
 use serde::{Deserialize, Serialize};
 use timely::dataflow::Scope;
 use crate::feature_encoders::column_encoder::ColumnEncoder;
-use crate::feature_encoders::standard_scaler::apply_scaling;
+use crate::feature_encoders::fitted_params::{export_pairs, import_pairs, ParamRecord};
+use crate::feature_encoders::inverse_column_encoder::InverseColumnEncoder;
+use crate::feature_encoders::standard_scaler::{apply_scaling, invert_scaling};
+use crate::operators::parallel_fit::{by_row_id, partitioned_aggregate};
+use crate::operators::republish::{republish_pairs, RepublishPolicy};
 use crate::types::row_value::RowValue;
 use crate::types::safe_f64::SafeF64;
 use crate::types::safe_hash_map::SafeHashMap;
@@ -128,22 +131,38 @@ impl<'de> Deserialize<'de> for MinMaxAggregate {
 
 pub struct MinMaxScaler<G: Scope> {
     meta: Option<Collection<G, (usize, (SafeF64, SafeF64))>>,
+    staleness: Option<Collection<G, (usize, usize)>>,
+    republish_policy: RepublishPolicy,
 }
 
 impl<G: Scope> MinMaxScaler<G> {
     pub fn new() -> Self{
-        Self{meta:None}
+        Self::new_with_republish_policy(RepublishPolicy::Always)
+    }
+
+    pub fn new_with_republish_policy(republish_policy: RepublishPolicy) -> Self{
+        Self{meta:None, staleness:None, republish_policy}
+    }
+
+    // number of changes of (min, range) that the republish policy holds back
+    pub fn staleness(&self) -> Collection<G, (usize, usize)> {
+        match &self.staleness {
+            None => panic!("called staleness before fit"),
+            Some(s) => s.clone()
+        }
     }
 }
 
 impl<G: Scope> ColumnEncoder<G> for MinMaxScaler<G>
 where G::Timestamp: Lattice+Ord {
     fn fit(&mut self, data: &Collection<G, (usize, RowValue)>) {
-        let meta = get_meta(&data.map(|x| (1, x)))
+        let (meta, staleness) = republish_pairs(&get_meta(&data.map(|x| (1, x))), self.republish_policy.clone());
+        let meta = meta
             .inspect(|(record, time, change)| {
                 println!("MinMaxScaler Meta: {:?}, time: {:?}, change: {:?}", record, time, change)
             });
         self.meta = Some(meta);
+        self.staleness = Some(staleness);
     }
 
     fn transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
@@ -153,15 +172,36 @@ where G::Timestamp: Lattice+Ord {
         };
         apply_scaling(&data.map(|x| (1, x)), &meta)
     }
+
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        self.meta.as_ref().map(export_pairs)
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        self.meta = Some(import_pairs(params));
+    }
+
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        input_names.map(|(pos, name)| (pos, format!("{}_minmax", name)))
+    }
+}
+
+impl<G: Scope> InverseColumnEncoder<G> for MinMaxScaler<G>
+where G::Timestamp: Lattice+Ord {
+    fn inverse_transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
+        let meta = match &self.meta {
+            None => panic!("called inverse_transform before fit"),
+            Some(m) => m
+        };
+        invert_scaling(&data.map(|x| (1, x)), &meta)
+    }
 }
 
 pub(crate) fn get_meta<G: Scope>(data: &Collection<G, (usize, (usize, RowValue))>) -> Collection<G, (usize, (SafeF64, SafeF64))>
 where G::Timestamp: Lattice+Ord {
-    data.threshold(|(_k, (_ix, value)), c| {
-            MinMaxAggregate::new((*value).get_float(), *c)
+    partitioned_aggregate(data, by_row_id, |(_ix, value), c| {
+            MinMaxAggregate::new((*value).get_float(), c)
         })
-        .map(|(column_id, _value)| column_id)
-        .count()
         .map(|(column, agg)| (column, agg.get()))
 }
 
@@ -1,12 +1,22 @@
 pub mod column_encoder;
+pub mod inverse_column_encoder;
+pub mod multi_input_encoder;
+pub mod row_encoder;
 pub mod one_hot_encoder;
 pub mod standard_scaler;
 pub mod multi_column_encoder;
 pub mod feature_extraction;
+pub mod feature_selection;
 pub mod polynomial_features_encoder;
 pub mod kbins_discretizer;
 pub mod minmax_scaler;
 pub mod pipeline;
+pub mod feature_union;
 pub mod passthrough;
 pub mod function_encoder;
 pub mod ordinal_encoder;
+pub mod feature_cross;
+pub mod normalizer;
+pub mod pca;
+pub mod fitted_params;
+pub mod online_transform;
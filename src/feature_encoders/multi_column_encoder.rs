@@ -1,10 +1,250 @@
-use differential_dataflow::Collection;
+use std::collections::HashSet;
+use differential_dataflow::{AsCollection, Collection};
 use differential_dataflow::lattice::Lattice;
-use differential_dataflow::operators::{Join};
+use differential_dataflow::operators::{Reduce};
 use timely::dataflow::Scope;
+use timely::dataflow::operators::ToStream;
+use timely::progress::Timestamp;
+use timely::worker::AsWorker;
 use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::feature_encoders::fitted_params::{child_params, nest_params, ParamRecord};
+use crate::feature_encoders::multi_input_encoder::MultiInputEncoder;
+use crate::feature_encoders::passthrough::Passthrough;
+use crate::feature_encoders::row_encoder::RowEncoder;
+use crate::operators::broadcast_join::broadcast_join;
 use crate::types::row::Row;
 use crate::types::row_value::RowValue;
+use crate::types::integer_assignment_aggregate::WidthPolicy;
+use crate::types::safe_f64::SafeF64;
+use crate::types::schema::{ColumnSelector, Schema};
+
+// how the columns that are not configured explicitly are handled (sklearn's remainder)
+pub enum Remainder<'a, G: Scope> {
+    Drop,
+    Passthrough,
+    // creates a fresh encoder for every remaining column
+    Encoder(Box<dyn Fn() -> Box<dyn ColumnEncoder<G> + 'a> + 'a>),
+}
+
+// creates an encoder for every column a ColumnSelector matches
+pub type EncoderFactory<'a, G> = Box<dyn Fn() -> Box<dyn ColumnEncoder<G> + 'a> + 'a>;
+
+// ColumnTransformer = sklearn's ColumnTransformer
+// as a RowEncoder it turns rows into rows of Float columns (one per output position), as a ColumnEncoder
+// it reads RowValue::Row or RowValue::Vec values and returns vectors, so it can be nested in a Pipeline
+pub struct ColumnTransformer<'a, G: Scope> {
+    config: Vec<(usize, Box<dyn ColumnEncoder<G> + 'a>)>,
+    multi_input_config: Vec<(Vec<usize>, Box<dyn MultiInputEncoder<G> + 'a>)>,
+    remainder_config: Vec<(usize, Box<dyn ColumnEncoder<G> + 'a>)>,
+    width_policy: Option<WidthPolicy>,
+}
+
+impl<'a, G: Scope> ColumnTransformer<'a, G>
+where G::Timestamp: Lattice+Ord {
+    // the segments are padded to the output width of their encoder during assembly, so the encoders can emit
+    // rows without the width (see ColumnEncoder::emit_cells)
+    pub fn new(mut config: Vec<(usize, Box<dyn ColumnEncoder<G> + 'a>)>) -> Self {
+        config.iter_mut().for_each(|(_, enc)| enc.emit_cells());
+        Self{config, multi_input_config: Vec::new(), remainder_config: Vec::new(), width_policy: None}
+    }
+
+    // the encoders in multi_input_config are applied to a tuple of columns, e.g. (vec![3, 5], Box::new(FeatureCross::new())),
+    // their outputs are appended after the single column encoders
+    pub fn with_multi_input(mut self, mut multi_input_config: Vec<(Vec<usize>, Box<dyn MultiInputEncoder<G> + 'a>)>) -> Self {
+        multi_input_config.iter_mut().for_each(|(_, enc)| enc.emit_cells());
+        // the tuples take their columns away from the remainder
+        self.remainder_config.retain(|(col_id, _)| !multi_input_config.iter().any(|(col_ids, _)| col_ids.contains(col_id)));
+        self.multi_input_config.extend(multi_input_config);
+        self
+    }
+
+    // every column of the schema (e.g. Schema::infer of the first row) that is not configured explicitly
+    // is handled by the remainder and appended last, in column order
+    pub fn with_remainder(mut self, remainder: Remainder<'a, G>, schema: &Schema) -> Self {
+        let configured: HashSet<usize> = self.config.iter().map(|(col_id, _)| *col_id)
+            .chain(self.multi_input_config.iter().flat_map(|(col_ids, _)| col_ids.iter().copied()))
+            .collect();
+        self.remainder_config = remainder_config(&configured, remainder, schema.len());
+        self.remainder_config.iter_mut().for_each(|(_, enc)| enc.emit_cells());
+        self
+    }
+
+    // reserves positions for every segment of the assembled vector (see reserved_width), so a growing encoder only
+    // moves the segments behind it in every row when it outgrows its reservation
+    pub fn with_width_policy(mut self, width_policy: WidthPolicy) -> Self {
+        self.width_policy = Some(width_policy);
+        self
+    }
+
+    // name based config, e.g. (ColumnSelector::Numeric, Box::new(|| Box::new(StandardScaler::new()) as Box<dyn ColumnEncoder<_>>)),
+    // see resolve_config
+    pub fn new_with_schema(schema: &Schema, config: Vec<(ColumnSelector, EncoderFactory<'a, G>)>) -> Self {
+        Self::new(resolve_config(schema, config))
+    }
+
+    // fits every encoder and returns the encoded (row id, RowValue::Vec) pairs, every column is sliced only once.
+    // configured columns come first, then the tuples of columns and the remainder last
+    pub fn fit_transform_vectors(&mut self, data: &Collection<G, (usize, Row)>) -> Collection<G, (usize, RowValue)> {
+        let mut encoded = fit_encode_columns(data, &mut self.config);
+        for (col_ids, enc) in self.multi_input_config.iter_mut() {
+            let cols = slice_columns(data, col_ids.clone());
+            enc.fit(&cols);
+            encoded.push(enc.transform(&cols));
+        }
+        encoded.extend(fit_encode_columns(data, &mut self.remainder_config));
+        concat_vectors(or_empty(encoded, data), self.output_widths(), self.width_policy)
+    }
+
+    // encoded (row id, RowValue::Vec) pairs, the building block for models that need whole vectors. a changed
+    // column retracts and re-sends the whole vector of its row, use transform_deltas or transform_cells where
+    // the update latency must not grow with the number of columns (see micro_benchmark_update_latency)
+    pub fn transform_vectors(&self, data: &Collection<G, (usize, Row)>) -> Collection<G, (usize, RowValue)> {
+        concat_vectors(self.encode(data), self.output_widths(), self.width_policy)
+    }
+
+    // (row id, (offset, segment)) deltas instead of whole vectors, a changed column only sends its own segment
+    pub fn transform_deltas(&self, data: &Collection<G, (usize, Row)>) -> Collection<G, (usize, (usize, RowValue))> {
+        position_deltas(self.encode(data), self.output_widths(), self.width_policy)
+    }
+
+    // (row id, (position, value)) updates of the non-zero cells, consumed by the materialized matrix sinks
+    pub fn transform_cells(&self, data: &Collection<G, (usize, Row)>) -> Collection<G, (usize, (usize, SafeF64))> {
+        position_cells(&self.transform_deltas(data))
+    }
+
+    fn encode(&self, data: &Collection<G, (usize, Row)>) -> Vec<Collection<G, (usize, RowValue)>> {
+        let mut encoded = encode_columns(data, &self.config);
+        for (col_ids, enc) in self.multi_input_config.iter() {
+            encoded.push(enc.transform(&slice_columns(data, col_ids.clone())));
+        }
+        encoded.extend(encode_columns(data, &self.remainder_config));
+        or_empty(encoded, data)
+    }
+
+    // the declared output width of every encoder, in the order of encode
+    fn output_widths(&self) -> Vec<Option<Collection<G, usize>>> {
+        let mut widths: Vec<_> = self.config.iter().map(|(_, enc)| enc.output_width(None)).collect();
+        widths.extend(self.multi_input_config.iter().map(|(_, enc)| enc.output_width()));
+        widths.extend(self.remainder_config.iter().map(|(_, enc)| enc.output_width(None)));
+        widths
+    }
+}
+
+// without any encoder (e.g. only a dropped remainder) every row is encoded as an empty vector
+fn or_empty<G: Scope>(encoded: Vec<Collection<G, (usize, RowValue)>>, data: &Collection<G, (usize, Row)>) -> Vec<Collection<G, (usize, RowValue)>>
+where
+    G::Timestamp: Lattice+Ord,{
+    if encoded.is_empty() {
+        vec![data.map(|(ix, _row)| (ix, RowValue::initial_vec()))]
+    } else {
+        encoded
+    }
+}
+
+fn fit_encode_columns<'a, G: Scope>(data: &Collection<G, (usize, Row)>, config: &mut Vec<(usize, Box<dyn ColumnEncoder<G> + 'a>)>) -> Vec<Collection<G, (usize, RowValue)>>
+where
+    G::Timestamp: Lattice+Ord,{
+    config.iter_mut().map(|(col_id, enc)| {
+        let col = slice_column(data, *col_id);
+        enc.fit(&col);
+        enc.transform(&col)
+    }).collect()
+}
+
+fn encode_columns<'a, G: Scope>(data: &Collection<G, (usize, Row)>, config: &Vec<(usize, Box<dyn ColumnEncoder<G> + 'a>)>) -> Vec<Collection<G, (usize, RowValue)>>
+where
+    G::Timestamp: Lattice+Ord,{
+    config.iter().map(|(col_id, enc)| enc.transform(&slice_column(data, *col_id))).collect()
+}
+
+impl<'a, G: Scope> RowEncoder<G> for ColumnTransformer<'a, G>
+where G::Timestamp: Lattice+Ord {
+    fn fit_rows(&mut self, data: &Collection<G, (usize, Row)>) {
+        for (col_id, enc) in self.config.iter_mut().chain(self.remainder_config.iter_mut()) {
+            enc.fit(&slice_column(data, *col_id));
+        }
+        for (col_ids, enc) in self.multi_input_config.iter_mut() {
+            enc.fit(&slice_columns(data, col_ids.clone()));
+        }
+    }
+
+    fn transform_rows(&self, data: &Collection<G, (usize, Row)>) -> Collection<G, (usize, Row)> {
+        self.transform_vectors(data).map(|(ix, val)| (ix, Row::from_row_value(val)))
+    }
+}
+
+impl<'a, G: Scope> ColumnEncoder<G> for ColumnTransformer<'a, G>
+where G::Timestamp: Lattice+Ord {
+    fn fit(&mut self, data: &Collection<G, (usize, RowValue)>) {
+        self.fit_rows(&data.map(|(ix, val)| (ix, Row::from_row_value(val))));
+    }
+
+    fn transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
+        self.transform_vectors(&data.map(|(ix, val)| (ix, Row::from_row_value(val))))
+    }
+
+    // the encoders are numbered in output order: configured columns, tuples of columns, remainder
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        let mut children: Vec<_> = self.config.iter().map(|(_, enc)| enc.export_params()).collect();
+        children.extend(self.multi_input_config.iter().map(|(_, enc)| enc.export_params()));
+        children.extend(self.remainder_config.iter().map(|(_, enc)| enc.export_params()));
+        nest_params(children)
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        let mut idx = 0;
+        for (_, enc) in self.config.iter_mut() {
+            enc.import_params(&child_params(params, idx));
+            idx += 1;
+        }
+        for (_, enc) in self.multi_input_config.iter_mut() {
+            enc.import_params(&child_params(params, idx));
+            idx += 1;
+        }
+        for (_, enc) in self.remainder_config.iter_mut() {
+            enc.import_params(&child_params(params, idx));
+            idx += 1;
+        }
+    }
+
+    // input_names are the (column, name) pairs of the rows, e.g. from column_names
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        let mut names: Vec<_> = self.config.iter()
+            .map(|(col_id, enc)| enc.feature_names(&column_name(input_names, *col_id)))
+            .collect();
+        for (col_ids, enc) in self.multi_input_config.iter() {
+            let col_ids = col_ids.clone();
+            names.push(enc.feature_names(&input_names.flat_map(move |(col_id, name)| {
+                col_ids.iter().position(|&c| c == col_id).map(|idx| (idx, name))
+            })));
+        }
+        names.extend(self.remainder_config.iter()
+            .map(|(col_id, enc)| enc.feature_names(&column_name(input_names, *col_id))));
+        if names.is_empty() {
+            return input_names.filter(|_| false);
+        }
+        concat_names(names, self.width_policy)
+    }
+}
+
+// the name of a single column as the (0, name) input name of its encoder
+fn column_name<G: Scope>(input_names: &Collection<G, (usize, String)>, col_id: usize) -> Collection<G, (usize, String)>
+where
+    G::Timestamp: Lattice+Ord,{
+    input_names.flat_map(move |(c, name)| if c == col_id { Some((0, name)) } else { None })
+}
+
+// (column, name) pairs for the given column names, as input for feature_names. only worker 0 emits them,
+// the names are broadcast or exchanged where they are needed
+pub fn column_names<G: Scope>(scope: &mut G, names: Vec<String>) -> Collection<G, (usize, String)>
+where
+    G::Timestamp: Lattice+Ord,{
+    let names = if scope.index() == 0 { names } else { Vec::new() };
+    names.into_iter().enumerate()
+        .map(|col_name| (col_name, G::Timestamp::minimum(), 1isize))
+        .to_stream(scope)
+        .as_collection()
+}
 
 // multi_column_encoder = sklearn's ColumnTransformer
 pub fn multi_column_encoder<'a, G: Scope>(
@@ -13,26 +253,583 @@ pub fn multi_column_encoder<'a, G: Scope>(
 ) -> Collection<G, RowValue>
 where
     G::Timestamp: Lattice+Ord,{
-    let _est_cols = config.len() - 1;
-    let mut col_iterator = config.into_iter();
-    // Handle the first element init out with Row with one value
-    // slice out current column
-    let (col_id, mut enc) = col_iterator.next().unwrap();
-    let col = data.map(move | (ix, row)|
-        (ix, row.values[col_id].clone()));
-    enc.fit(&col);
-    let mut out = enc.transform(&col);
-    // Process the rest using the same iterator
-    for (col_id, mut enc) in col_iterator {
-        // slice out current column
-        let col = data.map(move | (ix, row)|
-            (ix, row.values[col_id].clone()));
-        enc.fit(&col);
+    multi_column_encoder_with_ids(data, config).map(|(_ix, val)| val)
+}
+
+// same as multi_column_encoder, but the columns are selected by name or type with a ColumnSelector
+pub fn multi_column_encoder_with_schema<'a, G: Scope>(
+    data: &Collection<G, (usize, Row)>,
+    schema: &Schema,
+    config: Vec<(ColumnSelector, EncoderFactory<'a, G>)>
+) -> Collection<G, RowValue>
+where
+    G::Timestamp: Lattice+Ord,{
+    multi_column_encoder(data, resolve_config(schema, config))
+}
+
+// turns a name based config into the index based config: one encoder for every column a selector matches,
+// in config order and in column order for the columns of a selector. panics if a selector names a column
+// the schema doesn't have, a misspelled name would otherwise silently select nothing (or exclude nothing)
+pub fn resolve_config<'a, G: Scope>(
+    schema: &Schema,
+    config: Vec<(ColumnSelector, EncoderFactory<'a, G>)>
+) -> Vec<(usize, Box<dyn ColumnEncoder<G> + 'a>)>
+where
+    G::Timestamp: Lattice+Ord,{
+    config.into_iter()
+        .flat_map(|(selector, make_encoder)| {
+            let unknown = schema.unknown_names(&selector);
+            if !unknown.is_empty() {
+                panic!("column selector refers to unknown columns {:?}", unknown);
+            }
+            schema.select(&selector).into_iter()
+                .map(|col_id| (col_id, make_encoder()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+// same as multi_column_encoder, but keeps the row ids: (row id, RowValue::Vec)
+pub fn multi_column_encoder_with_ids<'a, G: Scope>(
+    data: &Collection<G, (usize, Row)>,
+    config: Vec<(usize, Box<dyn ColumnEncoder<G> + 'a>)>
+) -> Collection<G, (usize, RowValue)>
+where
+    G::Timestamp: Lattice+Ord,{
+    ColumnTransformer::new(config).fit_transform_vectors(data)
+}
+
+// same as multi_column_encoder, but also returns the (position, name) pairs of the output vectors, e.g. age_scaled
+// or workclass=Private. the names change along with the positions when categories or vocabularies change
+pub fn multi_column_encoder_with_names<'a, G: Scope>(
+    data: &Collection<G, (usize, Row)>,
+    column_names: &Collection<G, (usize, String)>,
+    config: Vec<(usize, Box<dyn ColumnEncoder<G> + 'a>)>
+) -> (Collection<G, RowValue>, Collection<G, (usize, String)>)
+where
+    G::Timestamp: Lattice+Ord,{
+    let mut transformer = ColumnTransformer::new(config);
+    let encoded = transformer.fit_transform_vectors(data).map(|(_ix, val)| val);
+    (encoded, transformer.feature_names(column_names))
+}
+
+// same as multi_column_encoder, but the encoders are fitted on fit_data (e.g. a training stream) and transform
+// transform_data (e.g. a validation or test stream), the rows of transform_data never change the fitted metadata
+pub fn multi_column_encoder_split<'a, G: Scope>(
+    fit_data: &Collection<G, (usize, Row)>,
+    transform_data: &Collection<G, (usize, Row)>,
+    config: Vec<(usize, Box<dyn ColumnEncoder<G> + 'a>)>
+) -> Collection<G, RowValue>
+where
+    G::Timestamp: Lattice+Ord,{
+    multi_column_encoder_split_with_ids(fit_data, transform_data, config).map(|(_ix, val)| val)
+}
 
-        out = out.join(&enc.transform(&col))
-            .map(|(ix, (row, row_val))| {
-                (ix, row.vector_append(&row_val))
+// same as multi_column_encoder_split, but keeps the row ids of transform_data: (row id, RowValue::Vec)
+pub fn multi_column_encoder_split_with_ids<'a, G: Scope>(
+    fit_data: &Collection<G, (usize, Row)>,
+    transform_data: &Collection<G, (usize, Row)>,
+    config: Vec<(usize, Box<dyn ColumnEncoder<G> + 'a>)>
+) -> Collection<G, (usize, RowValue)>
+where
+    G::Timestamp: Lattice+Ord,{
+    let mut transformer = ColumnTransformer::new(config);
+    transformer.fit_rows(fit_data);
+    transformer.transform_vectors(transform_data)
+}
+
+fn remainder_config<'a, G: Scope>(
+    configured: &HashSet<usize>,
+    remainder: Remainder<'a, G>,
+    n_columns: usize
+) -> Vec<(usize, Box<dyn ColumnEncoder<G> + 'a>)>
+where
+    G::Timestamp: Lattice+Ord,{
+    let remaining = (0..n_columns).filter(|col_id| !configured.contains(col_id));
+    match remainder {
+        Remainder::Drop => Vec::new(),
+        Remainder::Passthrough => remaining
+            .map(|col_id| (col_id, Box::new(Passthrough::new()) as Box<dyn ColumnEncoder<G> + 'a>))
+            .collect(),
+        Remainder::Encoder(make_encoder) => remaining
+            .map(|col_id| (col_id, make_encoder()))
+            .collect(),
+    }
+}
+
+// turns sparse vectors into dense ones, for sinks and models that need a RowValue::Vec per row
+pub fn densify<G: Scope>(data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)>
+where
+    G::Timestamp: Lattice+Ord,{
+    data.map(|(ix, val)| (ix, val.to_dense()))
+}
+
+// slice out a single column
+fn slice_column<G: Scope>(data: &Collection<G, (usize, Row)>, col_id: usize) -> Collection<G, (usize, RowValue)>
+where
+    G::Timestamp: Lattice+Ord,{
+    data.map(move | (ix, row)| (ix, row.values[col_id].clone()))
+}
+
+// slice out a tuple of columns
+fn slice_columns<G: Scope>(data: &Collection<G, (usize, Row)>, col_ids: Vec<usize>) -> Collection<G, (usize, Vec<RowValue>)>
+where
+    G::Timestamp: Lattice+Ord,{
+    data.map(move | (ix, row)|
+        (ix, col_ids.iter().map(|&col_id| row.values[col_id].clone()).collect::<Vec<_>>()))
+}
+
+// observed width of every encoder output: the longest segment it produced (scalars have width 1, sparse vectors their len)
+fn segment_widths<G: Scope>(segments: &Collection<G, (usize, (usize, RowValue))>) -> Collection<G, (usize, usize)>
+where
+    G::Timestamp: Lattice+Ord,{
+    segments
+        .map(|(_ix, (enc_idx, val))| (enc_idx, val.vector_len()))
+        .reduce(|_enc_idx, input, output| {
+            // values are sorted, so the last one is the max
+            output.push((*input[input.len() - 1].0, 1isize));
+        })
+}
+
+// the positions a segment of the given width takes up in the assembled vector. with WidthPolicy::Geometric the width
+// is rounded up along 1.5x steps, with WidthPolicy::FixedCapacity(n) to at least n positions, so the offsets of the
+// segments behind it (and with them every row) only change when it outgrows its reservation. the reserved positions
+// past the width are 0.0
+fn reserved_width(width: usize, width_policy: Option<WidthPolicy>) -> usize {
+    match width_policy {
+        Some(WidthPolicy::Geometric) => {
+            let mut reserved = width.min(1);
+            while reserved < width {
+                reserved = (reserved as f64 * 1.5).ceil() as usize;
+            }
+            reserved
+        }
+        Some(WidthPolicy::FixedCapacity(n)) => width.max(n),
+        Some(WidthPolicy::Exact) | None => width,
+    }
+}
+
+// (offset, reserved width) of every encoder output in the assembled vector, the offsets are the prefix sums of the
+// reserved widths in config order. only the offsets behind a segment whose reserved width changed are updated
+fn segment_offsets<G: Scope>(widths: &Collection<G, (usize, usize)>, width_policy: Option<WidthPolicy>) -> Collection<G, (usize, (usize, usize))>
+where
+    G::Timestamp: Lattice+Ord,{
+    widths
+        .map(move |(enc_idx, width)| ((), (enc_idx, reserved_width(width, width_policy))))
+        .reduce(|_key, input, output| {
+            let mut offset = 0;
+            for ((enc_idx, width), _) in input {
+                output.push(((*enc_idx, offset, *width), 1isize));
+                offset += *width;
+            }
+        })
+        .map(|(_key, (enc_idx, offset, width))| (enc_idx, (offset, width)))
+}
+
+// turns the encoded outputs into (row id, (offset, segment)) deltas, where offset is the position of the segment
+// in the assembled vector. a changed column only sends its own segment, the other segments of the row stay put
+// (only a change in the width of an encoder moves the segments behind it). declared_widths holds the output_width
+// of every encoder, the encoders without one get the width of their longest segment
+pub(crate) fn position_deltas<G: Scope>(
+    encoded: Vec<Collection<G, (usize, RowValue)>>,
+    declared_widths: Vec<Option<Collection<G, usize>>>,
+    width_policy: Option<WidthPolicy>
+) -> Collection<G, (usize, (usize, RowValue))>
+where
+    G::Timestamp: Lattice+Ord,{
+    tagged_deltas(encoded, declared_widths, width_policy).map(|(ix, ((offset, _enc_idx), (val, _width)))| (ix, (offset, val)))
+}
+
+// position deltas that also carry the encoder index and reserved width: (row id, ((offset, encoder index), (segment, width)))
+fn tagged_deltas<G: Scope>(
+    encoded: Vec<Collection<G, (usize, RowValue)>>,
+    declared_widths: Vec<Option<Collection<G, usize>>>,
+    width_policy: Option<WidthPolicy>
+) -> Collection<G, (usize, ((usize, usize), (RowValue, usize)))>
+where
+    G::Timestamp: Lattice+Ord,{
+    let segments = encoded.into_iter().enumerate()
+        .map(|(enc_idx, enc_out)| enc_out.map(move |(ix, val)| (ix, (enc_idx, val))))
+        .reduce(|all, enc_out| all.concat(&enc_out))
+        .expect("no encoder configured");
+    let declared: HashSet<usize> = declared_widths.iter().enumerate()
+        .filter(|(_, width)| width.is_some())
+        .map(|(enc_idx, _)| enc_idx)
+        .collect();
+    let observed = segment_widths(&segments.filter(move |(_ix, (enc_idx, _val))| !declared.contains(enc_idx)));
+    let widths = declared_widths.into_iter().enumerate()
+        .filter_map(|(enc_idx, width)| width.map(|width| width.map(move |width| (enc_idx, width))))
+        .fold(observed, |all, width| all.concat(&width));
+    let offsets = segment_offsets(&widths, width_policy);
+    broadcast_join(&segments.map(|(ix, (enc_idx, val))| (enc_idx, (ix, val))), &offsets)
+        .map(|(enc_idx, ((ix, val), (offset, width)))| (ix, ((offset, enc_idx), (val, width))))
+}
+
+// the non-zero cells of the assembled vectors: (row id, (position, value)). when a segment changes, only the cells
+// whose value actually changed are retracted and re-inserted, so a sink can apply them in place
+pub fn position_cells<G: Scope>(deltas: &Collection<G, (usize, (usize, RowValue))>) -> Collection<G, (usize, (usize, SafeF64))>
+where
+    G::Timestamp: Lattice+Ord,{
+    deltas
+        .flat_map(|(ix, (offset, val))| {
+            val.nonzero_entries().into_iter()
+                .map(move |(pos, value)| (ix, (offset + pos, SafeF64(value))))
+                .collect::<Vec<_>>()
+        })
+        .consolidate()
+}
+
+// composes the (position, name) pairs of the encoders like concat_vectors composes their outputs: the width of an
+// encoder is its last named position + 1 and its names are shifted by the reserved widths of the encoders before it
+pub(crate) fn concat_names<G: Scope>(names: Vec<Collection<G, (usize, String)>>, width_policy: Option<WidthPolicy>) -> Collection<G, (usize, String)>
+where
+    G::Timestamp: Lattice+Ord,{
+    let tagged = names.into_iter().enumerate()
+        .map(|(enc_idx, enc_names)| enc_names.map(move |(pos, name)| (enc_idx, (pos, name))))
+        .reduce(|all, enc_names| all.concat(&enc_names))
+        .expect("no encoder configured");
+    let widths = tagged
+        .map(|(enc_idx, (pos, _name))| (enc_idx, pos + 1))
+        .reduce(|_enc_idx, input, output| {
+            // values are sorted, so the last one is the max
+            output.push((*input[input.len() - 1].0, 1isize));
+        });
+    let offsets = segment_offsets(&widths, width_policy);
+    // the reserved positions past the width of an encoder are named like the unused positions of position_names
+    let reserved = broadcast_join(&widths, &offsets)
+        .flat_map(|(_enc_idx, (width, (offset, reserved)))| {
+            (width..reserved).map(move |pos| (offset + pos, format!("__reserved_{}", offset + pos)))
+        });
+    broadcast_join(&tagged, &offsets)
+        .map(|(_enc_idx, ((pos, name), (offset, _width)))| (offset + pos, name))
+        .concat(&reserved)
+}
+
+// assembles the encoded outputs into one vector per row, in config order. a row only shows up once every
+// encoder produced its segment, the vector is sparse as soon as one segment is sparse (see RowValue::vector_append).
+// shorter segments are padded to the reserved width of their encoder, so the positions match position_cells and
+// concat_names. the whole vector of a row is rebuilt and re-sent when one of its segments changes, so the cost of
+// an update grows with the number of encoders, unlike with position_deltas
+pub(crate) fn concat_vectors<G: Scope>(
+    encoded: Vec<Collection<G, (usize, RowValue)>>,
+    declared_widths: Vec<Option<Collection<G, usize>>>,
+    width_policy: Option<WidthPolicy>
+) -> Collection<G, (usize, RowValue)>
+where
+    G::Timestamp: Lattice+Ord,{
+    let n_segments = encoded.len();
+    tagged_deltas(encoded, declared_widths, width_policy)
+        .reduce(move |_ix, input, output| {
+            // segments are sorted by offset, the encoder index breaks ties between empty segments
+            if input.len() == n_segments {
+                let vec = input.iter()
+                    .fold(RowValue::initial_vec(), |vec, ((_position, (val, width)), _)| {
+                        vec.vector_append(&val.clone().padded(*width))
+                    });
+                output.push((vec, 1isize));
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use crate::feature_encoders::one_hot_encoder::OneHotEncoder;
+    use crate::feature_encoders::standard_scaler::StandardScaler;
+    use crate::types::schema::LogicalType;
+    use super::*;
+    #[test]
+    fn position_cells_only_send_changed_cells() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = ColumnTransformer::new(vec![
+                    (0, Box::new(Passthrough::new()) as Box<dyn ColumnEncoder<_>>),
+                    (1, Box::new(Passthrough::new()))
+                ]);
+                enc.fit_rows(&input_df);
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+
+                enc.transform_cells(&input_df)
+                    .inspect(move |((ix, (pos, x)), time, diff)| {
+                        let mut out = output_clone.lock().unwrap();
+                        out.push((*time, *ix, *pos, x.0, *diff));
+                    })
+                    .probe()
+            });
+
+            input.advance_to(0);
+            for ix in 0 .. 4 {
+                input.insert((ix, Row::with_integer_vec(vec![ix as i64, 2 * ix as i64])));
+            }
+            input.advance_to(1);
+            input.remove((2, Row::with_integer_vec(vec![2, 4])));
+            input.insert((2, Row::with_integer_vec(vec![2, 5])));
+
+            input.advance_to(2);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // lock the Mutex to access data
+            let mut output = output.lock().unwrap();
+            output.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+            // zero cells are skipped, the update of row 2 only touches position 1
+            let expected_values = vec![
+                (0, 1, 0, 1.0, 1), (0, 1, 1, 2.0, 1),
+                (0, 2, 0, 2.0, 1), (0, 2, 1, 4.0, 1),
+                (0, 3, 0, 3.0, 1), (0, 3, 1, 6.0, 1),
+                (1, 2, 1, 4.0, -1), (1, 2, 1, 5.0, 1),
+            ];
+            assert_eq!(&*output, &expected_values, "Transformed output is incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+
+    #[test]
+    fn split_encoder_only_fits_on_fit_data() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut train = InputSession::new();
+            let mut test = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let train_df = train.to_collection(scope);
+                let test_df = test.to_collection(scope);
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+
+                multi_column_encoder_split_with_ids(&train_df, &test_df, vec![
+                    (0, Box::new(StandardScaler::new()) as Box<dyn ColumnEncoder<_>>)
+                ])
+                    .inspect(move |((ix, x), _, diff)| {
+                        let mut out = output_clone.lock().unwrap();
+                        out.push((*ix, x.get_vec().clone(), *diff));
+                    })
+                    .probe()
+            });
+
+            train.advance_to(0);
+            test.advance_to(0);
+            for ix in 0 .. 10 {
+                train.insert((ix, Row::with_integer_vec(vec![ix as i64])));
+            }
+            test.insert((100, Row::with_integer_vec(vec![20])));
+
+            train.advance_to(1);
+            test.advance_to(1);
+            train.flush();
+            test.flush();
+            worker.step_while(|| probe.less_than(train.time()));
+
+            // only the test row is encoded, with the mean and variance of the training rows
+            let output = output.lock().unwrap();
+            assert_eq!(&*output, &vec![(100, vec![(20.0 - 4.5) / 8.25], 1)], "Transformed output is incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+
+    #[test]
+    fn feature_names_follow_new_categories() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let names = column_names(scope, vec!["age".to_string(), "workclass".to_string()]);
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+
+                let (_encoded, feature_names) = multi_column_encoder_with_names(&input_df, &names, vec![
+                    (0, Box::new(StandardScaler::new()) as Box<dyn ColumnEncoder<_>>),
+                    (1, Box::new(OneHotEncoder::new_with_width_policy(WidthPolicy::Exact))),
+                ]);
+                feature_names
+                    .inspect(move |((pos, name), _, diff)| {
+                        let mut out = output_clone.lock().unwrap();
+                        out.push((name.clone(), *pos, *diff));
+                    })
+                    .probe()
             });
+
+            input.advance_to(0);
+            input.insert((0, Row::with_row_values(vec![RowValue::Integer(30), RowValue::Text("a".to_string())])));
+            input.insert((1, Row::with_row_values(vec![RowValue::Integer(40), RowValue::Text("b".to_string())])));
+            input.advance_to(1);
+            input.insert((2, Row::with_row_values(vec![RowValue::Integer(50), RowValue::Text("c".to_string())])));
+            input.advance_to(2);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // accumulate the name updates of both times
+            let mut names: Vec<(String, usize)> = Vec::new();
+            for (name, pos, diff) in output.lock().unwrap().iter() {
+                if *diff > 0 {
+                    names.push((name.clone(), *pos));
+                } else {
+                    names.retain(|x| x != &(name.clone(), *pos));
+                }
+            }
+            names.sort();
+
+            // the scaled age comes first, every category gets its own position after it
+            let labels: Vec<&str> = names.iter().map(|(name, _)| name.as_str()).collect();
+            assert_eq!(labels, vec!["age_scaled", "workclass=a", "workclass=b", "workclass=c"], "Feature names are incorrect");
+            let mut positions: Vec<usize> = names.iter().map(|(_, pos)| *pos).collect();
+            positions.sort();
+            assert_eq!(positions, vec![0, 1, 2, 3], "Feature positions are incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+
+    #[test]
+    fn new_categories_only_send_their_own_rows() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let deltas = Arc::new(Mutex::new(Vec::new()));
+            let vectors = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = ColumnTransformer::new(vec![
+                    (0, Box::new(Passthrough::new()) as Box<dyn ColumnEncoder<_>>),
+                    (1, Box::new(OneHotEncoder::new_with_width_policy(WidthPolicy::Exact))),
+                ]);
+                enc.fit_rows(&input_df);
+                let deltas_clone = Arc::clone(&deltas); // Clone Arc for use inside closure
+                let vectors_clone = Arc::clone(&vectors);
+
+                enc.transform_vectors(&input_df)
+                    .inspect(move |((ix, x), _, diff)| {
+                        vectors_clone.lock().unwrap().push((*ix, x.to_dense().get_vec().clone(), *diff));
+                    });
+                enc.transform_deltas(&input_df)
+                    .inspect(move |((ix, _), time, _)| deltas_clone.lock().unwrap().push((*time, *ix)))
+                    .probe()
+            });
+
+            input.advance_to(0);
+            input.insert((0, Row::with_row_values(vec![RowValue::Integer(30), RowValue::Text("a".to_string())])));
+            input.insert((1, Row::with_row_values(vec![RowValue::Integer(40), RowValue::Text("b".to_string())])));
+            input.advance_to(1);
+            input.insert((2, Row::with_row_values(vec![RowValue::Integer(50), RowValue::Text("c".to_string())])));
+            input.advance_to(2);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // the width grows with the third category, the one-hot segments of the first rows stay put
+            let deltas = deltas.lock().unwrap();
+            assert!(deltas.iter().all(|(time, ix)| *time == 0 || *ix == 2), "Unchanged rows were sent again");
+
+            // the assembled vectors are padded to the current width
+            let mut current: Vec<(usize, Vec<f64>)> = Vec::new();
+            for (ix, x, diff) in vectors.lock().unwrap().iter() {
+                if *diff > 0 {
+                    current.push((*ix, x.clone()));
+                } else {
+                    current.retain(|y| y != &(*ix, x.clone()));
+                }
+            }
+            current.sort_by_key(|(ix, _)| *ix);
+            assert_eq!(current.len(), 3, "Transformed output is incorrect");
+            for (_ix, x) in current.iter() {
+                assert_eq!(x.len(), 4, "Vector width is incorrect");
+            }
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+
+    #[test]
+    fn reserved_widths_keep_the_following_segments_in_place() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let deltas = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = ColumnTransformer::new(vec![
+                    (0, Box::new(OneHotEncoder::new_with_width_policy(WidthPolicy::Exact)) as Box<dyn ColumnEncoder<_>>),
+                    (1, Box::new(Passthrough::new())),
+                ]).with_width_policy(WidthPolicy::Geometric);
+                enc.fit_rows(&input_df);
+                let deltas_clone = Arc::clone(&deltas); // Clone Arc for use inside closure
+
+                enc.transform_deltas(&input_df)
+                    .inspect(move |((ix, (offset, _)), time, diff)| deltas_clone.lock().unwrap().push((*time, *ix, *offset, *diff)))
+                    .probe()
+            });
+
+            // four categories reserve five positions, the fifth category fits in
+            input.advance_to(0);
+            for (ix, category) in ["a", "b", "c", "d"].iter().enumerate() {
+                input.insert((ix, Row::with_row_values(vec![RowValue::Text(category.to_string()), RowValue::Integer(ix as i64)])));
+            }
+            input.advance_to(1);
+            input.insert((4, Row::with_row_values(vec![RowValue::Text("e".to_string()), RowValue::Integer(4)])));
+            input.advance_to(2);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            let deltas = deltas.lock().unwrap();
+            assert!(deltas.iter().all(|(time, ix, _, _)| *time == 0 || *ix == 4), "The segments behind the one-hot column moved");
+            assert!(deltas.iter().all(|(_, _, offset, _)| *offset == 0 || *offset == 5), "Offsets are incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+        assert_eq!((0..10).map(|width| reserved_width(width, Some(WidthPolicy::Geometric))).collect::<Vec<_>>(),
+                   vec![0, 1, 2, 3, 5, 5, 8, 8, 8, 12]);
+    }
+
+    #[test]
+    fn remainder_handles_unconfigured_columns() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let schema = Schema::new(vec![
+                    ("a".to_string(), LogicalType::Integer),
+                    ("b".to_string(), LogicalType::Integer),
+                    ("c".to_string(), LogicalType::Integer),
+                ]);
+                let configs: Vec<ColumnTransformer<_>> = vec![
+                    ColumnTransformer::new(vec![(1, Box::new(Passthrough::new()) as Box<dyn ColumnEncoder<_>>)])
+                        .with_remainder(Remainder::Passthrough, &schema),
+                    ColumnTransformer::new(vec![(1, Box::new(Passthrough::new()) as Box<dyn ColumnEncoder<_>>)])
+                        .with_remainder(Remainder::Encoder(Box::new(|| Box::new(StandardScaler::new()) as Box<dyn ColumnEncoder<_>>)), &schema),
+                    // no encoder at all
+                    ColumnTransformer::new(vec![]).with_remainder(Remainder::Drop, &schema),
+                ];
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+
+                configs.into_iter().enumerate()
+                    .map(|(config_idx, mut enc)| enc.fit_transform_vectors(&input_df).map(move |(ix, x)| (config_idx, ix, x)))
+                    .reduce(|all, encoded| all.concat(&encoded))
+                    .unwrap()
+                    .inspect(move |((config_idx, ix, x), _, _)| {
+                        output_clone.lock().unwrap().push((*config_idx, *ix, x.to_dense().get_vec().clone()));
+                    })
+                    .probe()
+            });
+
+            input.advance_to(0);
+            input.insert((0, Row::with_integer_vec(vec![1, 2, 3])));
+            input.insert((1, Row::with_integer_vec(vec![3, 4, 5])));
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // lock the Mutex to access data
+            let mut output = output.lock().unwrap();
+            output.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+            // the configured column comes first, then the remaining columns in column order
+            assert_eq!(&*output, &vec![
+                (0, 0, vec![2.0, 1.0, 3.0]),
+                (0, 1, vec![4.0, 3.0, 5.0]),
+                (1, 0, vec![2.0, -1.0, -1.0]),
+                (1, 1, vec![4.0, 1.0, 1.0]),
+                (2, 0, vec![]),
+                (2, 1, vec![]),
+            ], "Transformed output is incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
     }
-    out.map(|(_ix, val)| val)
-}
\ No newline at end of file
+}
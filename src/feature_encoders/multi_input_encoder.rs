@@ -0,0 +1,53 @@
+use differential_dataflow::Collection;
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Reduce;
+use timely::dataflow::{Scope};
+use crate::feature_encoders::fitted_params::ParamRecord;
+use crate::types::row_value::RowValue;
+
+pub trait MultiInputEncoder<G: Scope>
+where
+    G::Timestamp: Lattice+Ord,
+{
+    /// Fits the encoder on a tuple of input columns and stores metadata internally (in the struct)
+    fn fit(&mut self, data: &Collection<G, (usize, Vec<RowValue>)>);
+
+    /// Transforms the tuple of input columns into a single output value using the internally stored metadata
+    fn transform(&self, data: &Collection<G, (usize, Vec<RowValue>)>) -> Collection<G, (usize, RowValue)>;
+
+    /// Names of the output positions as (position, name) pairs, given the (input index, name) pairs of the input
+    /// columns. The default is a single position named after the crossed input names
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        crossed_name(input_names).map(|((), name)| (0, name))
+    }
+
+    /// Width of the output vectors. None means the width is the length of the longest output vector
+    fn output_width(&self) -> Option<Collection<G, usize>> {
+        None
+    }
+
+    /// Lets an encoder with an output_width emit rows that end at their last non-zero position (see RowValue::cells),
+    /// see ColumnEncoder::emit_cells
+    fn emit_cells(&mut self) {
+    }
+
+    /// Returns the fitted metadata as a collection of records, None if the encoder keeps no fitted state
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        None
+    }
+
+    /// Uses the given records as metadata, transform then works without fit
+    fn import_params(&mut self, _params: &Collection<G, ParamRecord>) {
+    }
+}
+
+// the input names joined in input order, e.g. age_x_workclass
+pub(crate) fn crossed_name<G: Scope>(input_names: &Collection<G, (usize, String)>) -> Collection<G, ((), String)>
+where G::Timestamp: Lattice+Ord {
+    input_names
+        .map(|(idx, name)| ((), (idx, name)))
+        .reduce(|_key, input, output| {
+            let names: Vec<String> = input.iter().map(|((_idx, name), _)| name.clone()).collect();
+            output.push((names.join("_x_"), 1isize));
+        })
+}
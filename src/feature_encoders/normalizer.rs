@@ -0,0 +1,93 @@
+use differential_dataflow::Collection;
+use differential_dataflow::lattice::Lattice;
+use timely::dataflow::Scope;
+use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::types::row_value::RowValue;
+
+// Normalizer = sklearn's Normalizer, scales every vector to unit l2 norm
+pub struct Normalizer<G: Scope> {
+    phantom_data: Option<Collection<G, (usize, RowValue)>>
+}
+
+impl<G: Scope> Normalizer<G> {
+    pub fn new() -> Self{
+        Self { phantom_data: None }
+    }
+}
+
+impl<G: Scope> ColumnEncoder<G> for Normalizer<G>
+where G::Timestamp: Lattice+Ord {
+    fn fit(&mut self, _data: &Collection<G, (usize, RowValue)>) {
+
+    }
+
+    fn transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
+        data.map(|(ix, val)| {
+            let norm = val.nonzero_entries().iter().map(|(_, x)| x * x).sum::<f64>().sqrt();
+            if norm == 0.0 {
+                return (ix, val);
+            }
+            match val {
+                RowValue::Sparse { len, indices, values } =>
+                    (ix, RowValue::Sparse { len, indices, values: values.iter().map(|x| x / norm).collect() }),
+                // a scalar (e.g. from a StandardScaler) is a vector of length one
+                RowValue::Integer(_) | RowValue::Float(_) => (ix, RowValue::Float(val.get_float() / norm)),
+                val => (ix, RowValue::Vec(val.get_vec().iter().map(|x| x / norm).collect())),
+            }
+        })
+    }
+
+    // keeps every position where it is
+    fn output_width(&self, input_width: Option<Collection<G, usize>>) -> Option<Collection<G, usize>> {
+        input_width
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use super::*;
+    #[test]
+    fn normalizer_scales_to_unit_norm() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = Normalizer::new();
+                enc.fit(&input_df);
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+
+                enc.transform(&input_df)
+                    .inspect(move |((ix, x), _, _)| {
+                        let mut out = output_clone.lock().unwrap();
+                        out.push((*ix, x.clone()));
+                    })
+                    .probe()
+            });
+
+            input.advance_to(0);
+            input.insert((0, RowValue::Vec(vec![3.0, 4.0])));
+            input.insert((1, RowValue::sparse(4, vec![(3, -2.0)])));
+            // a zero vector has no direction and stays as it is
+            input.insert((2, RowValue::Vec(vec![0.0, 0.0])));
+            input.insert((3, RowValue::Float(-2.5)));
+            input.insert((4, RowValue::Integer(3)));
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            let mut output = output.lock().unwrap();
+            output.sort_by_key(|(ix, _)| *ix);
+            assert_eq!(&*output, &vec![
+                (0, RowValue::Vec(vec![0.6, 0.8])),
+                (1, RowValue::sparse(4, vec![(3, -1.0)])),
+                (2, RowValue::Vec(vec![0.0, 0.0])),
+                (3, RowValue::Float(-1.0)),
+                (4, RowValue::Float(1.0)),
+            ], "Normalized output is incorrect");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+}
@@ -1,30 +1,46 @@
-use differential_dataflow::Collection;
+use std::collections::HashMap;
+use std::hash::Hash;
+use differential_dataflow::{Collection, ExchangeData};
 use differential_dataflow::lattice::Lattice;
-use differential_dataflow::operators::{Count, Join, Threshold};
 use timely::dataflow::{Scope};
 use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::feature_encoders::fitted_params::{export_positions, import_positions, ParamRecord};
+use crate::feature_encoders::inverse_column_encoder::InverseColumnEncoder;
+use crate::operators::broadcast_join::{broadcast_antijoin, broadcast_join};
+use crate::operators::parallel_fit::assign_positions;
 use crate::types::row_value::RowValue;
 use crate::types::safe_hash_map::SafeHashMap;
-use crate::types::integer_assignment_aggregate::PositionAssignmentAggregate;
+use crate::types::integer_assignment_aggregate::{PositionAssignmentAggregate, WidthPolicy};
 
 pub struct OneHotEncoder <G: Scope> {
     value_positions: Option<Collection<G, ((), (SafeHashMap<RowValue, usize>, usize))>>,
+    width_policy: Option<WidthPolicy>,
+    cells: bool,
 }
 
 impl<G: Scope> OneHotEncoder<G> {
     pub fn new() -> Self<>{
-        Self{value_positions:None}
+        Self{value_positions:None, width_policy:None, cells:false}
+    }
+
+    // with a fixed or growing-only width, rows are only re-emitted when their own value changes or the width grows
+    pub fn new_with_width_policy(width_policy: WidthPolicy) -> Self<>{
+        Self{value_positions:None, width_policy:Some(width_policy), cells:false}
+    }
+
+    // the current vector width, a resize shows up as a retraction of the old and an insertion of the new width
+    pub fn widths(&self) -> Collection<G, usize> {
+        match &self.value_positions {
+            None => panic!("called widths before fit"),
+            Some(m) => m.map(|(_, (_, len))| len)
+        }
     }
 }
 
 impl<G: Scope> ColumnEncoder<G> for OneHotEncoder<G>
 where G::Timestamp: Lattice+Ord {
     fn fit(&mut self, data: &Collection<G, (usize, RowValue)>) {
-        let distinct = data.map(|(_, row_value)| row_value).distinct();
-        self.value_positions = Some(distinct
-            .threshold(|value, multiplicity|
-                PositionAssignmentAggregate::new_with_val(value, *multiplicity))
-            .map(|_vector| ()).count().map(|((), agg)| ((), agg.get_map_and_len())));
+        self.value_positions = Some(fit_positions(&data.map(|(_, row_value)| row_value), self.width_policy));
     }
 
     fn transform(&self, data: &Collection<G,(usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
@@ -32,30 +48,227 @@ where G::Timestamp: Lattice+Ord {
             None => panic!("called transform before fit"),
             Some(m) => m
         };
-        let value_pos_pairs = value_positions.flat_map(|(_, (btree, len))| {
-            btree.0.into_iter().map(move |(key, value)| (key, (value, len)))
-        }).inspect(|(record, time, change)| {
-            println!("OneHot Meta: {:?}, time: {:?}, change: {:?}", record, time, change)
-        });
+        let encoded = one_hot(data, value_positions);
+        if self.cells { encoded } else { pad_to_width(&encoded, &self.widths()) }
+    }
+
+    fn emit_cells(&mut self) {
+        self.cells = true;
+    }
+
+    fn output_width(&self, _input_width: Option<Collection<G, usize>>) -> Option<Collection<G, usize>> {
+        self.value_positions.as_ref().map(|_| self.widths())
+    }
+
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        self.value_positions.as_ref().map(|m| export_positions(m, |value| value))
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        self.value_positions = Some(import_positions(params, |value| value));
+    }
 
-        let len_collection = value_positions.map(|(_, (_ , len))| ((), len));
-        let data = data.map(|(i, v) | (v, i));
+    // column=value for every position
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        let value_positions = match &self.value_positions {
+            None => panic!("called feature_names before fit"),
+            Some(m) => m
+        };
+        position_names(&input_names.map(|(_pos, name)| ((), name)), value_positions, "=", RowValue::label)
+    }
+}
 
-        let inner_join = data.join(&value_pos_pairs).map(|(value, (row_id, (vector_index, len)))| {
-            let mut vec = vec![0f64; len];
-            vec[vector_index] = 1.0;
-            (row_id, RowValue::Vec(vec))
+impl<G: Scope> InverseColumnEncoder<G> for OneHotEncoder<G>
+where G::Timestamp: Lattice+Ord {
+    // every vector (e.g. class scores) is decoded to the value at its largest position. zero vectors (unknown values)
+    // and the shared overflow bucket of a fixed capacity can not be decoded and are dropped
+    fn inverse_transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
+        let value_positions = match &self.value_positions {
+            None => panic!("called inverse_transform before fit"),
+            Some(m) => m
+        };
+        let position_values = value_positions.flat_map(|(_, (positions, _len))| {
+            let mut counts: HashMap<usize, usize> = HashMap::new();
+            for position in positions.0.values() {
+                *counts.entry(*position).or_insert(0) += 1;
+            }
+            positions.0.into_iter()
+                .filter(|(_, position)| counts[position] == 1)
+                .map(|(value, position)| (position, value))
+                .collect::<Vec<_>>()
         });
+        let decoded_positions = data.flat_map(|(ix, vector)| argmax(&vector).map(|position| (position, ix)));
+        broadcast_join(&decoded_positions, &position_values)
+            .map(|(_position, (ix, value))| (ix, value))
+    }
+}
+
+// position of the largest entry, None for an all zero vector
+fn argmax(vector: &RowValue) -> Option<usize> {
+    if vector.nonzero_entries().is_empty() {
+        return None;
+    }
+    vector.to_dense().get_vec().iter().enumerate()
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .map(|(position, _)| position)
+}
+
+// (position, name{sep}label of the value) for every position. positions shared by several values (the overflow bucket
+// of a fixed capacity) are named other, positions without a value (reserved by a growing width) unused
+pub(crate) fn position_names<G: Scope, K>(
+    names: &Collection<G, ((), String)>,
+    value_positions: &Collection<G, ((), (SafeHashMap<K, usize>, usize))>,
+    sep: &'static str,
+    label: fn(&K) -> String
+) -> Collection<G, (usize, String)>
+where G::Timestamp: Lattice+Ord, K: ExchangeData + Hash {
+    broadcast_join(names, value_positions)
+        .flat_map(move |((), (name, (positions, len)))| {
+            let mut labels: Vec<Vec<String>> = vec![Vec::new(); len];
+            for (value, position) in positions.iter() {
+                if *position < len {
+                    labels[*position].push(label(value));
+                }
+            }
+            labels.into_iter().enumerate()
+                .map(|(position, labels)| match labels.len() {
+                    0 => (position, format!("{}{}__unused_{}", name, sep, position)),
+                    1 => (position, format!("{}{}{}", name, sep, labels[0])),
+                    _ => (position, format!("{}{}__other", name, sep)),
+                })
+                .collect::<Vec<_>>()
+        })
+}
+
+// assigns a vector position to every distinct value
+pub(crate) fn fit_positions<G: Scope, K>(values: &Collection<G, K>, width_policy: Option<WidthPolicy>) -> Collection<G, ((), (SafeHashMap<K, usize>, usize))>
+where G::Timestamp: Lattice+Ord, K: ExchangeData + Hash {
+    assign_positions(&values.map(|value| ((), value)), move |value, multiplicity|
+            PositionAssignmentAggregate::new_with_val(value, multiplicity, width_policy))
+        .map(|((), agg)| ((), agg.get_map_and_len()))
+}
+
+// one-hot encodes the values with the positions from fit_positions into sparse vectors that end at their position
+// (see RowValue::cells), unknown values become empty vectors. the width is not part of the rows, so a new value
+// only touches its own rows, pad_to_width turns them into vectors of the full width
+pub(crate) fn one_hot<G: Scope, K>(data: &Collection<G, (usize, K)>, value_positions: &Collection<G, ((), (SafeHashMap<K, usize>, usize))>) -> Collection<G, (usize, RowValue)>
+where G::Timestamp: Lattice+Ord, K: ExchangeData + Hash {
+    let value_pos_pairs = value_positions.flat_map(|(_, (btree, _len))| btree.0.into_iter());
+    let data = data.map(|(i, v) | (v, i));
+
+    let inner_join = broadcast_join(&data, &value_pos_pairs).map(|(_value, (row_id, vector_index))| {
+        (row_id, RowValue::cells(vec![(vector_index, 1.0)]))
+    });
+
+    let unmatched = broadcast_antijoin(&data, &value_pos_pairs.map(|(value, _)| value))
+        .map(|(_value, row_id)| (row_id, RowValue::cells(vec![])));
+
+    inner_join.concat(&unmatched)
+}
+
+// pads the rows to the current width, a resize re-emits every row
+pub(crate) fn pad_to_width<G: Scope>(data: &Collection<G, (usize, RowValue)>, widths: &Collection<G, usize>) -> Collection<G, (usize, RowValue)>
+where G::Timestamp: Lattice+Ord {
+    broadcast_join(&data.map(|row| ((), row)), &widths.map(|len| ((), len)))
+        .map(|((), ((row_id, vector), len))| (row_id, vector.padded(len)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use super::*;
+    #[test]
+    fn one_hot_fixed_capacity_uses_overflow_bucket() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = OneHotEncoder::new_with_width_policy(WidthPolicy::FixedCapacity(2));
+                enc.fit(&input_df);
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+
+                enc.transform(&input_df)
+                    .inspect(move |((_, x), _, _)| {
+                        let mut out = output_clone.lock().unwrap();
+                        out.push((x.vector_len(), x.nonzero_entries()[0].0));
+                    })
+                    .probe()
+            });
+
+            input.advance_to(0);
+            for person in 0 .. 4 {
+                input.insert((person, RowValue::Integer(person as i64)));
+            }
 
-        let unmatched = data
-            .antijoin(&value_pos_pairs.map(|(value, _)| value))
-            .map(|(v, i)| ((), (v, i)))
-            .join(&len_collection)
-            .map(|(_, ((value, row_id), len))| {
-                let vec = vec![0f64; len];
-                (row_id, RowValue::Vec(vec))
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // lock the Mutex to access data
+            let mut output = output.lock().unwrap();
+            output.sort();
+
+            // two values get their own position, the other two share the overflow bucket
+            assert_eq!(&*output, &vec![(3, 0), (3, 1), (3, 2), (3, 2)], "Transformed output is incorrect");
         });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
 
-        inner_join.concat(&unmatched)
+    #[test]
+    fn positions_stay_put_when_values_arrive() {
+        let result = timely::execute(timely::Config::process(2), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+                fit_positions(&input.to_collection(scope), None)
+                    .inspect(move |(((), (positions, _len)), time, change)| {
+                        if *change > 0 {
+                            output_clone.lock().unwrap().push((*time, positions.0.clone()));
+                        }
+                    })
+                    .probe()
+            });
+
+            input.advance_to(0);
+            if worker.index() == 0 {
+                for value in 0 .. 20 {
+                    input.insert(RowValue::Integer(value));
+                }
+            }
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            if worker.index() == 0 {
+                input.remove(RowValue::Integer(3));
+            }
+            input.advance_to(2);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            if worker.index() == 0 {
+                input.insert(RowValue::Integer(20));
+            }
+            input.advance_to(3);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // only the worker of the final stage sees the positions
+            let output = output.lock().unwrap();
+            if output.is_empty() {
+                return;
+            }
+            let before = &output.iter().find(|(time, _)| *time == 0).unwrap().1;
+            let after = &output.iter().find(|(time, _)| *time == 2).unwrap().1;
+            for (value, position) in before.iter().filter(|(value, _)| **value != RowValue::Integer(3)) {
+                assert_eq!(after.get(value), Some(position), "position of {:?} moved", value);
+            }
+            // the new value takes the position freed by the retracted one
+            assert_eq!(after.get(&RowValue::Integer(20)), before.get(&RowValue::Integer(3)));
+        });
+        assert!(result.is_ok(), "Timely execution failed");
     }
-}
\ No newline at end of file
+}
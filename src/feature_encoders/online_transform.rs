@@ -0,0 +1,159 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use differential_dataflow::AsCollection;
+use differential_dataflow::input::InputSession;
+use differential_dataflow::lattice::Lattice;
+use timely::dataflow::{ProbeHandle, Scope};
+use timely::dataflow::operators::Exchange;
+use timely::progress::Timestamp;
+use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::types::row_value::RowValue;
+
+// Query side of a fitted encoder for serving. The encoder is fitted on the training stream as usual, the rows submitted
+// here only go through transform, so they are encoded with the live parameters but never change them.
+// The results are gathered on worker 0, so submit and read the queries there (the other workers only advance).
+pub struct OnlineTransform<T: Timestamp+Lattice+Ord> {
+    queries: InputSession<T, (usize, RowValue), isize>,
+    // request id -> (time the request was submitted at, submitted value)
+    submitted: HashMap<usize, (T, RowValue)>,
+    // result updates whose time is not complete yet
+    pending: Rc<RefCell<Vec<(usize, T, RowValue, isize)>>>,
+    // request id -> encoded value -> count, accumulated over all completed times
+    results: HashMap<usize, BTreeMap<RowValue, isize>>,
+    probe: ProbeHandle<T>,
+}
+
+impl<T: Timestamp+Lattice+Ord> OnlineTransform<T> {
+    pub fn new() -> Self {
+        Self {
+            queries: InputSession::new(),
+            submitted: HashMap::new(),
+            pending: Rc::new(RefCell::new(Vec::new())),
+            results: HashMap::new(),
+            probe: ProbeHandle::new(),
+        }
+    }
+
+    // call inside the dataflow that fitted the encoder, e.g. with a ColumnTransformer after fit_rows
+    pub fn attach<G: Scope<Timestamp=T>>(&mut self, scope: &mut G, encoder: &dyn ColumnEncoder<G>) {
+        let pending = Rc::clone(&self.pending);
+        encoder.transform(&self.queries.to_collection(scope))
+            .inner
+            .exchange(|_update| 0)
+            .as_collection()
+            .inspect(move |((request_id, val), time, diff)| {
+                pending.borrow_mut().push((*request_id, time.clone(), val.clone(), *diff));
+            })
+            .probe_with(&mut self.probe);
+    }
+
+    // submits a value at the current time of the query input, rows for a ColumnTransformer go in as Row::into_row_value
+    pub fn submit(&mut self, request_id: usize, value: RowValue) {
+        self.queries.insert((request_id, value.clone()));
+        self.submitted.insert(request_id, (self.queries.time().clone(), value));
+    }
+
+    // advance together with the training input, the results of a request are ready once the probe passed its time
+    pub fn advance_to(&mut self, time: T) {
+        self.queries.advance_to(time);
+        self.queries.flush();
+    }
+
+    pub fn time(&self) -> &T {
+        self.queries.time()
+    }
+
+    pub fn probe(&self) -> &ProbeHandle<T> {
+        &self.probe
+    }
+
+    // the encoded value of the request with the parameters of the latest completed time,
+    // None while the request is still in flight
+    pub fn result(&mut self, request_id: usize) -> Option<RowValue> {
+        self.apply_complete();
+        let (submitted_at, _) = self.submitted.get(&request_id)?;
+        if self.probe.less_equal(submitted_at) {
+            return None;
+        }
+        self.results.get(&request_id)?.iter().find(|(_, count)| **count > 0).map(|(val, _)| val.clone())
+    }
+
+    // folds the updates of all completed times into the results and drops them, updates of removed requests
+    // (e.g. the retraction that remove caused) are skipped
+    fn apply_complete(&mut self) {
+        let mut pending = self.pending.borrow_mut();
+        let (complete, rest): (Vec<_>, Vec<_>) = pending.drain(..)
+            .partition(|(_, time, _, _)| !self.probe.less_equal(time));
+        *pending = rest;
+        for (request_id, _time, val, diff) in complete {
+            if !self.submitted.contains_key(&request_id) {
+                continue;
+            }
+            let counts = self.results.entry(request_id).or_default();
+            let count = counts.entry(val.clone()).or_insert(0);
+            *count += diff;
+            if *count == 0 {
+                counts.remove(&val);
+            }
+        }
+    }
+
+    // retracts the request from the query input, so later parameter changes no longer re-encode it
+    pub fn remove(&mut self, request_id: usize) {
+        if let Some((_, value)) = self.submitted.remove(&request_id) {
+            self.queries.remove((request_id, value));
+        }
+        self.results.remove(&request_id);
+        self.pending.borrow_mut().retain(|(id, _, _, _)| *id != request_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use differential_dataflow::input::InputSession;
+    use crate::feature_encoders::standard_scaler::StandardScaler;
+    use super::*;
+    #[test]
+    fn queries_use_live_parameters_without_fitting_on_them() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let mut online = OnlineTransform::new();
+            worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = StandardScaler::new();
+                enc.fit(&input_df);
+                online.attach(scope, &enc);
+            });
+
+            input.advance_to(0);
+            for person in 0 .. 10 {
+                input.insert((person, RowValue::Integer(person as i64)));
+            }
+            online.submit(0, RowValue::Integer(9));
+
+            input.advance_to(1);
+            input.flush();
+            online.advance_to(1);
+            worker.step_while(|| online.probe().less_than(online.time()));
+
+            // the query is scaled with the statistics of the training rows only
+            assert_eq!(online.result(0).map(|x| x.get_float()), Some((9.0 - 4.5) / 8.25));
+
+            // a new training row changes the parameters, the pending query is re-encoded
+            input.insert((10, RowValue::Integer(10)));
+            input.advance_to(2);
+            input.flush();
+            online.advance_to(2);
+            worker.step_while(|| online.probe().less_than(online.time()));
+
+            assert_eq!(online.result(0).map(|x| x.get_float()), Some((9.0 - 5.0) / 10.0));
+            // the updates of the completed times are folded into the current value
+            assert!(online.pending.borrow().is_empty(), "Completed updates are still buffered");
+            assert_eq!(online.results[&0].len(), 1);
+            online.remove(0);
+            assert_eq!(online.result(0), None);
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+}
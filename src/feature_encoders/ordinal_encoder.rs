@@ -1,8 +1,11 @@
 use differential_dataflow::Collection;
 use differential_dataflow::lattice::Lattice;
-use differential_dataflow::operators::{Count, Join, Threshold};
 use timely::dataflow::{Scope};
 use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::feature_encoders::fitted_params::ParamRecord;
+use crate::feature_encoders::inverse_column_encoder::InverseColumnEncoder;
+use crate::operators::broadcast_join::{broadcast_antijoin, broadcast_join};
+use crate::operators::parallel_fit::assign_positions;
 use crate::types::row_value::RowValue;
 use crate::types::integer_assignment_aggregate::PositionAssignmentAggregate;
 
@@ -19,11 +22,8 @@ impl<G: Scope> OrdinalEncoder<G> {
 impl<G: Scope> ColumnEncoder<G> for OrdinalEncoder<G>
 where G::Timestamp: Lattice+Ord {
     fn fit(&mut self, data: &Collection<G, (usize, RowValue)>) {
-        let distinct = data.map(|(_, row_value)| row_value).distinct();
-        self.value_map = Some(distinct
-            .threshold(|value, multiplicity| PositionAssignmentAggregate::new_with_val(value, *multiplicity))
-            .map(|_vector| ())
-            .count()
+        self.value_map = Some(assign_positions(&data.map(|(_, row_value)| ((), row_value)), |value, multiplicity|
+                PositionAssignmentAggregate::new_with_val(value, multiplicity, None))
             .flat_map(|((), agg)| agg.get_map_and_len().0.iter()
                 .map(|(k,v)| (k.clone(), v.clone())).collect::<Vec<_>>())
             .map(|(k, v)| (k, RowValue::Float(v as f64)))
@@ -37,18 +37,45 @@ where G::Timestamp: Lattice+Ord {
             Some(m) => m
         };
 
-        let joined = data.map(|(rix, v) | (v, rix) )
-            .join(value_map);
+        let joined = broadcast_join(&data.map(|(rix, v) | (v, rix) ), value_map);
 
         let matched = joined.map(|(_, (rix, v))| (rix, v));
 
-        let unmatched = data.map(|(row_id, v)| (v, row_id))
-            .antijoin(&value_map.map(|(value, _)| value))
+        let unmatched = broadcast_antijoin(&data.map(|(row_id, v)| (v, row_id)), &value_map.map(|(value, _)| value))
             .map(|(_, row_id)| {
                 (row_id, RowValue::Float(-1f64))
             });
 
         matched.concat(&unmatched)
     }
+
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        self.value_map.as_ref().map(|m| m.map(|(value, encoded)| ParamRecord::Mapping(value, encoded)))
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        self.value_map = Some(params.flat_map(|record| match record {
+            ParamRecord::Mapping(value, encoded) => Some((value, encoded)),
+            _ => None,
+        }));
+    }
+
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        input_names.map(|(pos, name)| (pos, format!("{}_ordinal", name)))
+    }
+}
+
+impl<G: Scope> InverseColumnEncoder<G> for OrdinalEncoder<G>
+where G::Timestamp: Lattice+Ord {
+    // codes are rounded to the closest category, the code of unknown values (-1) has no category and is dropped
+    fn inverse_transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
+        let value_map = match &self.value_map {
+            None => panic!("called inverse_transform before fit"),
+            Some(m) => m
+        };
+        let codes = data.map(|(ix, code)| (RowValue::Float(code.get_float().round()), ix));
+        broadcast_join(&codes, &value_map.map(|(value, code)| (code, value)))
+            .map(|(_code, (ix, value))| (ix, value))
+    }
 }
 
@@ -23,4 +23,9 @@ where G::Timestamp: Lattice+Ord {
     fn transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
         data.clone()
     }
+
+    // keeps every position where it is
+    fn output_width(&self, input_width: Option<Collection<G, usize>>) -> Option<Collection<G, usize>> {
+        input_width
+    }
 }
\ No newline at end of file
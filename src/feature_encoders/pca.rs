@@ -0,0 +1,309 @@
+use differential_dataflow::Collection;
+use differential_dataflow::difference::{Abelian, IsZero, Monoid, Semigroup};
+use differential_dataflow::lattice::Lattice;
+use serde::{Deserialize, Serialize};
+use timely::dataflow::Scope;
+use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::feature_encoders::fitted_params::ParamRecord;
+use crate::operators::broadcast_join::broadcast_join;
+use crate::operators::parallel_fit::{by_row_id, partitioned_aggregate};
+use crate::types::row_value::RowValue;
+use crate::types::safe_f64::SafeF64;
+
+// count, sums and sums of products of the vector positions. the products of the positions (i, j) with i <= j
+// are packed column by column, so a wider vector (e.g. a new category) only appends to both vectors
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) struct CovarianceAggregate {
+    sums: Vec<SafeF64>,
+    products: Vec<SafeF64>,
+    count: isize,
+}
+
+impl CovarianceAggregate {
+    pub(crate) fn new(value: &RowValue, multiplicity: isize) -> Self {
+        let width = value.vector_len();
+        let m = multiplicity as f64;
+        let entries = value.nonzero_entries();
+        let mut sums = vec![SafeF64(0.0); width];
+        let mut products = vec![SafeF64(0.0); width * (width + 1) / 2];
+        for &(j, y) in entries.iter() {
+            sums[j] = SafeF64(y * m);
+            for &(i, x) in entries.iter().filter(|(i, _)| *i <= j) {
+                products[packed(i, j)] = SafeF64(x * y * m);
+            }
+        }
+        Self { sums, products, count: multiplicity }
+    }
+
+    // (mean, covariance matrix)
+    pub(crate) fn get(&self) -> (Vec<f64>, Vec<Vec<f64>>) {
+        let count = self.count as f64;
+        let mean: Vec<f64> = self.sums.iter().map(|sum| sum.0 / count).collect();
+        let width = mean.len();
+        let mut covariance = vec![vec![0f64; width]; width];
+        for j in 0..width {
+            for i in 0..=j {
+                let c = self.products[packed(i, j)].0 / count - mean[i] * mean[j];
+                covariance[i][j] = c;
+                covariance[j][i] = c;
+            }
+        }
+        (mean, covariance)
+    }
+}
+
+fn packed(i: usize, j: usize) -> usize {
+    j * (j + 1) / 2 + i
+}
+
+fn add_assign(target: &mut Vec<SafeF64>, other: &[SafeF64]) {
+    if target.len() < other.len() {
+        target.resize(other.len(), SafeF64(0.0));
+    }
+    for (t, o) in target.iter_mut().zip(other.iter()) {
+        t.0 += o.0;
+    }
+}
+
+impl IsZero for CovarianceAggregate {
+    fn is_zero(&self) -> bool {
+        self.count == 0
+    }
+}
+
+impl Semigroup for CovarianceAggregate {
+    fn plus_equals(&mut self, other: &Self) {
+        self.count += other.count;
+        add_assign(&mut self.sums, &other.sums);
+        add_assign(&mut self.products, &other.products);
+    }
+}
+
+impl Monoid for CovarianceAggregate {
+    fn zero() -> Self {
+        Self { sums: Vec::new(), products: Vec::new(), count: 0 }
+    }
+}
+
+impl Abelian for CovarianceAggregate {
+    fn negate(&mut self) {
+        for sum in self.sums.iter_mut().chain(self.products.iter_mut()) {
+            sum.0 = -sum.0;
+        }
+        self.count *= -1;
+    }
+}
+
+// (mean, components), every component is a unit vector of the input width
+type Components = (Vec<SafeF64>, Vec<Vec<SafeF64>>);
+
+// Pca = sklearn's PCA, projects the centered vectors on the n_components directions of largest variance.
+// the mean and the covariance matrix are maintained incrementally (retractions included), the components are
+// recomputed whenever the covariance matrix changes, so unlike the scalers every new row moves every output row
+pub struct Pca<G: Scope> {
+    components: Option<Collection<G, ((), Components)>>,
+    n_components: usize,
+}
+
+impl<G: Scope> Pca<G> {
+    pub fn new(n_components: usize) -> Self {
+        Self{components:None, n_components}
+    }
+}
+
+impl<G: Scope> ColumnEncoder<G> for Pca<G>
+where G::Timestamp: Lattice+Ord {
+    fn fit(&mut self, data: &Collection<G, (usize, RowValue)>) {
+        let n_components = self.n_components;
+        // dense vectors can't be hashed, the aggregation works on their sparse form
+        let sparse = data.map(|(ix, value)| ((), (ix, RowValue::sparse(value.vector_len(), value.nonzero_entries()))));
+        self.components = Some(partitioned_aggregate(&sparse, by_row_id, |(_ix, value), c| CovarianceAggregate::new(value, c))
+            .map(move |((), agg)| {
+                let (mean, covariance) = agg.get();
+                let components = principal_components(covariance, n_components);
+                ((), (to_safe(mean), components.into_iter().map(to_safe).collect()))
+            }));
+    }
+
+    fn transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
+        let components = match &self.components {
+            None => panic!("called transform before fit"),
+            Some(c) => c
+        };
+        broadcast_join(&data.map(|x| ((), x)), components)
+            .map(|((), ((ix, value), (mean, components)))| {
+                let entries = value.nonzero_entries();
+                let projected = components.iter()
+                    .map(|component| {
+                        let dot: f64 = entries.iter()
+                            .filter(|(i, _)| *i < component.len())
+                            .map(|(i, x)| x * component[*i].0)
+                            .sum();
+                        let offset: f64 = mean.iter().zip(component.iter()).map(|(m, c)| m.0 * c.0).sum();
+                        dot - offset
+                    })
+                    .collect();
+                (ix, RowValue::Vec(projected))
+            })
+    }
+
+    fn output_width(&self, _input_width: Option<Collection<G, usize>>) -> Option<Collection<G, usize>> {
+        self.components.as_ref().map(|c| c.map(|((), (_mean, components))| components.len()))
+    }
+
+    // the mean first, then the components
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        self.components.as_ref().map(|c| c.map(|((), (mean, components))| {
+            ParamRecord::Rows(std::iter::once(mean).chain(components).collect())
+        }))
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        self.components = Some(params.flat_map(|record| match record {
+            ParamRecord::Rows(mut rows) if !rows.is_empty() => {
+                let mean = rows.remove(0);
+                Some(((), (mean, rows)))
+            }
+            _ => None,
+        }));
+    }
+
+    fn feature_names(&self, _input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        let components = match &self.components {
+            None => panic!("called feature_names before fit"),
+            Some(c) => c
+        };
+        components.flat_map(|((), (_mean, components))| (0..components.len()).map(|i| (i, format!("pca{}", i))))
+    }
+}
+
+fn to_safe(values: Vec<f64>) -> Vec<SafeF64> {
+    values.into_iter().map(SafeF64).collect()
+}
+
+// the eigenvectors of the n largest eigenvalues, the largest entry of every eigenvector is made positive
+// so the signs don't flip between two fits
+fn principal_components(covariance: Vec<Vec<f64>>, n: usize) -> Vec<Vec<f64>> {
+    let mut eigen = symmetric_eigen(covariance);
+    eigen.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    eigen.into_iter()
+        .take(n)
+        .map(|(_value, mut vector)| {
+            let largest = vector.iter().copied().fold(0f64, |largest, x| if x.abs() > largest.abs() { x } else { largest });
+            if largest < 0.0 {
+                vector.iter_mut().for_each(|x| *x = -*x);
+            }
+            vector
+        })
+        .collect()
+}
+
+// (eigenvalue, eigenvector) pairs of a symmetric matrix, cyclic jacobi rotations until the matrix is diagonal
+fn symmetric_eigen(mut a: Vec<Vec<f64>>) -> Vec<(f64, Vec<f64>)> {
+    let n = a.len();
+    let mut v: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+    for _sweep in 0..100 {
+        let off_diagonal: f64 = (0..n).flat_map(|p| ((p + 1)..n).map(move |q| (p, q))).map(|(p, q)| a[p][q] * a[p][q]).sum();
+        if off_diagonal < 1e-24 {
+            break;
+        }
+        for p in 0..n {
+            for q in (p + 1)..n {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+                // the rotation that zeroes a[p][q]
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                let (row_p, row_q) = (a[p].clone(), a[q].clone());
+                for (k, (apk, aqk)) in row_p.into_iter().zip(row_q).enumerate() {
+                    a[p][k] = c * apk - s * aqk;
+                    a[q][k] = s * apk + c * aqk;
+                }
+                for row in v.iter_mut() {
+                    let (vkp, vkq) = (row[p], row[q]);
+                    row[p] = c * vkp - s * vkq;
+                    row[q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    (0..n).map(|i| (a[i][i], v.iter().map(|row| row[i]).collect())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use super::*;
+    #[test]
+    fn pca_follows_the_data() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let mut enc = Pca::new(1);
+                enc.fit(&input_df);
+
+                enc.transform(&input_df)
+                    .inspect(move |((ix, x), _, diff)| {
+                        let mut out = output_clone.lock().unwrap();
+                        out.push((*ix, x.get_vec()[0], *diff));
+                    })
+                    .probe()
+            });
+
+            // points on the line y = 2x, the component is (1, 2) / sqrt(5)
+            input.advance_to(0);
+            for person in 0 .. 4 {
+                input.insert((person, RowValue::Vec(vec![person as f64, 2.0 * person as f64])));
+            }
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            let sqrt5 = 5f64.sqrt();
+            let projected = |output: &Vec<(usize, f64, isize)>| {
+                let mut current: Vec<(usize, f64)> = Vec::new();
+                for (ix, x, diff) in output.iter() {
+                    if *diff > 0 {
+                        current.push((*ix, *x));
+                    } else {
+                        let pos = current.iter().position(|(i, y)| i == ix && (y - x).abs() < 1e-9).unwrap();
+                        current.remove(pos);
+                    }
+                }
+                current.sort_by_key(|(ix, _)| *ix);
+                current
+            };
+            // the mean is (1.5, 3)
+            let current = projected(&output.lock().unwrap());
+            assert_eq!(current.len(), 4, "No output was generated");
+            for (ix, x) in current.iter() {
+                assert!((x - (*ix as f64 - 1.5) * sqrt5).abs() < 1e-9, "Projection is incorrect");
+            }
+
+            // the mean moves to (1, 2) and every row is projected again
+            input.remove((3, RowValue::Vec(vec![3.0, 6.0])));
+            input.advance_to(2);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            let current = projected(&output.lock().unwrap());
+            assert_eq!(current.len(), 3, "The retracted row is still there");
+            for (ix, x) in current.iter() {
+                assert!((x - (*ix as f64 - 1.0) * sqrt5).abs() < 1e-9, "Projection is incorrect after the retraction");
+            }
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+}
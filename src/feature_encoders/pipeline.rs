@@ -2,6 +2,9 @@ use differential_dataflow::Collection;
 use differential_dataflow::lattice::Lattice;
 use timely::dataflow::{Scope};
 use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::feature_encoders::fitted_params::{child_params, nest_params, ParamRecord};
+use crate::feature_encoders::row_encoder::RowEncoder;
+use crate::types::row::Row;
 use crate::types::row_value::RowValue;
 
 pub struct Pipeline<'a, G : Scope> {
@@ -31,4 +34,99 @@ where G::Timestamp: Lattice+Ord {
         }
         intermediate
     }
-}
\ No newline at end of file
+
+    // the width flows through the encoders like the data does
+    fn output_width(&self, input_width: Option<Collection<G, usize>>) -> Option<Collection<G, usize>> {
+        self.config.iter().fold(input_width, |width, encoder| encoder.output_width(width))
+    }
+
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        nest_params(self.config.iter().map(|encoder| encoder.export_params()).collect())
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        for (idx, encoder) in self.config.iter_mut().enumerate() {
+            encoder.import_params(&child_params(params, idx));
+        }
+    }
+
+    // the names flow through the encoders like the data does
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        let mut names = input_names.clone();
+        for encoder in &self.config {
+            names = encoder.feature_names(&names);
+        }
+        names
+    }
+}
+
+// the whole row enters the pipeline as a RowValue::Row, so the first stage is usually a ColumnTransformer,
+// e.g. ColumnTransformer -> PCA -> Normalizer -> ColumnTransformer, vector outputs become rows of Float columns
+impl<'a, G: Scope> RowEncoder<G> for Pipeline<'a, G>
+where G::Timestamp: Lattice+Ord {
+    fn fit_rows(&mut self, data: &Collection<G, (usize, Row)>) {
+        self.fit(&data.map(|(ix, row)| (ix, row.into_row_value())));
+    }
+
+    fn transform_rows(&self, data: &Collection<G, (usize, Row)>) -> Collection<G, (usize, Row)> {
+        self.transform(&data.map(|(ix, row)| (ix, row.into_row_value())))
+            .map(|(ix, val)| (ix, Row::from_row_value(val)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use crate::feature_encoders::multi_column_encoder::ColumnTransformer;
+    use crate::feature_encoders::normalizer::Normalizer;
+    use crate::feature_encoders::passthrough::Passthrough;
+    use crate::feature_encoders::pca::Pca;
+    use crate::feature_encoders::standard_scaler::StandardScaler;
+    use super::*;
+    #[test]
+    fn nested_pipeline_keeps_the_row_ids() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let config: Vec<(usize, Box<dyn ColumnEncoder<_>>)> = vec![
+                    (0, Box::new(StandardScaler::new())),
+                    (1, Box::new(Passthrough::new())),
+                ];
+                // column transformer -> pca -> normalizer, a single component normalizes to +-1
+                let mut pipeline = Pipeline::new(vec![
+                    Box::new(ColumnTransformer::new(config)),
+                    Box::new(Pca::new(1)),
+                    Box::new(Normalizer::new()),
+                ]);
+                pipeline.fit_rows(&input_df);
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+
+                pipeline.transform_rows(&input_df)
+                    .inspect(move |((ix, row), _, _)| {
+                        let mut out = output_clone.lock().unwrap();
+                        out.push((*ix, row.clone()));
+                    })
+                    .probe()
+            });
+
+            input.advance_to(0);
+            for person in 10 .. 14 {
+                input.insert((person, Row::with_integer_vec(vec![person as i64, 2 * person as i64])));
+            }
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            let mut output = output.lock().unwrap();
+            output.sort_by_key(|(ix, _)| *ix);
+            let expected: Vec<(usize, Row)> = vec![(10, -1.0), (11, -1.0), (12, 1.0), (13, 1.0)].into_iter()
+                .map(|(ix, x)| (ix, Row::with_row_value(RowValue::Float(x))))
+                .collect();
+            assert_eq!(&*output, &expected, "Row ids were not preserved");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+}
@@ -71,6 +71,13 @@ where G::Timestamp: Lattice+Ord {
             })
         })
     }
+
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        let min_degree = self.min_degree;
+        let max_degree = self.max_degree;
+        input_names.flat_map(move |(_pos, name)| (min_degree..=max_degree)
+            .map(move |degree| (degree - min_degree, format!("{}^{}", name, degree))))
+    }
 }
 
 fn polynomials_1d(value: f64, min_degree: usize, max_degree: usize) -> RowValue {
@@ -0,0 +1,15 @@
+use differential_dataflow::Collection;
+use differential_dataflow::lattice::Lattice;
+use timely::dataflow::{Scope};
+use crate::types::row::Row;
+
+pub trait RowEncoder<G: Scope>
+where
+    G::Timestamp: Lattice+Ord,
+{
+    /// Fits the encoder on whole rows and stores metadata internally (in the struct)
+    fn fit_rows(&mut self, data: &Collection<G, (usize, Row)>);
+
+    /// Transforms whole rows using the internally stored metadata, the row ids are preserved
+    fn transform_rows(&self, data: &Collection<G, (usize, Row)>) -> Collection<G, (usize, Row)>;
+}
@@ -1,26 +1,37 @@
 use differential_dataflow::Collection;
 use differential_dataflow::difference::{Abelian, IsZero, Monoid, Semigroup};
 use differential_dataflow::lattice::Lattice;
-use differential_dataflow::operators::{Join, Threshold,Count};
 use serde::{Deserialize, Serialize};
 use timely::dataflow::{Scope};
 use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::feature_encoders::fitted_params::{export_pairs, import_pairs, ParamRecord};
+use crate::feature_encoders::inverse_column_encoder::InverseColumnEncoder;
+use crate::operators::broadcast_join::broadcast_join;
+use crate::operators::parallel_fit::{by_row_id, partitioned_aggregate};
+use crate::operators::republish::{republish_pairs, RepublishPolicy};
 use crate::types::row_value::RowValue;
 use crate::types::safe_f64::SafeF64;
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
-struct VarianceAggregate {
+pub(crate) struct VarianceAggregate {
     mean: SafeF64,
     m2: SafeF64,
     count: isize
 }
 
 impl VarianceAggregate {
-    fn new(value: f64, multiplicity: isize) -> Self {
+    pub(crate) fn new(value: f64, multiplicity: isize) -> Self {
         Self { mean: SafeF64(value), m2: SafeF64(0.0), count: multiplicity, }
     }
 
-    fn get(&self) -> (SafeF64, SafeF64) {
+    // the aggregate of the same values plus as many zeros as it takes to reach n_values values
+    pub(crate) fn with_zeros(&self, n_values: isize) -> Self {
+        let mut padded = self.clone();
+        padded.plus_equals(&Self::new(0.0, n_values - self.count));
+        padded
+    }
+
+    pub(crate) fn get(&self) -> (SafeF64, SafeF64) {
         // variance = M2 / count
         (SafeF64(self.mean.0),SafeF64( self.m2.0 / (self.count as f64)))
     }
@@ -62,39 +73,47 @@ impl Abelian for VarianceAggregate {
 
 pub struct StandardScaler < G: Scope> {
     mean: Option<Collection<G, (usize, (SafeF64, SafeF64))>>,
-    round_to: Option<(i32, i32)>,
+    staleness: Option<Collection<G, (usize, usize)>>,
+    republish_policy: RepublishPolicy,
 }
 
 impl<G: Scope> StandardScaler<G> {
     pub fn new() -> Self{
-        Self{mean:None, round_to:None}
+        Self::new_with_republish_policy(RepublishPolicy::Always)
     }
 
     pub fn new_with_rounding(n_mean: i32, n_var: i32) -> Self{
-        Self{mean:None, round_to: Some((n_mean,n_var))}
+        Self::new_with_republish_policy(RepublishPolicy::DecimalRounding(vec![n_mean, n_var]))
+    }
+
+    pub fn new_with_republish_policy(republish_policy: RepublishPolicy) -> Self{
+        Self{mean:None, staleness:None, republish_policy}
+    }
+
+    // number of changes of (mean, variance) that the republish policy holds back
+    pub fn staleness(&self) -> Collection<G, (usize, usize)> {
+        match &self.staleness {
+            None => panic!("called staleness before fit"),
+            Some(s) => s.clone()
+        }
     }
 }
 
 impl<G: Scope> ColumnEncoder<G> for StandardScaler<G>
 where G::Timestamp: Lattice+Ord {
     fn fit(&mut self, data: &Collection<G, (usize, RowValue)>) {
-        let mean_var_raw = data.map(|x| (1, x))
-            .threshold(|(_k, (_ix, value)), c| {
-                VarianceAggregate::new((*value).get_float(), *c)
-            })
-            .map(|(column_id, _value)| column_id)
-            .count();
-        let mean_var = match self.round_to {
-            None => mean_var_raw
-                .map(|(column, mean_aggregate)| (column, mean_aggregate.get())),
-            Some((n1, n2)) => mean_var_raw.
-                map(move |(column, mean_aggregate)| (column, round_to_decimal(mean_aggregate.get(), n1, n2)))
-        };
+        let mean_var_raw = partitioned_aggregate(&data.map(|x| (1, x)), by_row_id, |(_ix, value), c| {
+                VarianceAggregate::new((*value).get_float(), c)
+            });
+        let (mean_var, staleness) = republish_pairs(
+            &mean_var_raw.map(|(column, mean_aggregate)| (column, mean_aggregate.get())),
+            self.republish_policy.clone());
         let mean_var = mean_var
             .inspect(|(record, time, change)| {
                 println!("StandardScaler Meta: {:?}, time: {:?}, change: {:?}", record, time, change)
             });
         self.mean = Some(mean_var);
+        self.staleness = Some(staleness);
     }
 
     fn transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
@@ -104,19 +123,42 @@ where G::Timestamp: Lattice+Ord {
         };
         apply_scaling(&data.map(|x| (1, x)), &mean)
     }
+
+    fn export_params(&self) -> Option<Collection<G, ParamRecord>> {
+        self.mean.as_ref().map(export_pairs)
+    }
+
+    fn import_params(&mut self, params: &Collection<G, ParamRecord>) {
+        self.mean = Some(import_pairs(params));
+    }
+
+    fn feature_names(&self, input_names: &Collection<G, (usize, String)>) -> Collection<G, (usize, String)> {
+        input_names.map(|(pos, name)| (pos, format!("{}_scaled", name)))
+    }
+}
+
+impl<G: Scope> InverseColumnEncoder<G> for StandardScaler<G>
+where G::Timestamp: Lattice+Ord {
+    fn inverse_transform(&self, data: &Collection<G, (usize, RowValue)>) -> Collection<G, (usize, RowValue)> {
+        let mean = match &self.mean {
+            None => panic!("called inverse_transform before fit"),
+            Some(m) => m
+        };
+        invert_scaling(&data.map(|x| (1, x)), &mean)
+    }
 }
 
 pub(crate) fn apply_scaling<G: Scope>(data: &Collection<G, (usize, (usize, RowValue))>, mean: &Collection<G, (usize, (SafeF64, SafeF64))>) -> Collection<G, (usize, RowValue)>
 where G::Timestamp: Lattice+Ord {
-    data.join(&mean)
+    broadcast_join(data, mean)
         .map(|(_key, ((ix, val), (mean, var)))| (ix, RowValue::Float((val.get_float() - mean.0) / var.0)))
 }
 
-
-fn round_to_decimal((m,v): (SafeF64, SafeF64), n1: i32, n2: i32) -> (SafeF64, SafeF64) {
-    let factor1 = 10f64.powi(n1);
-    let factor2 = 10f64.powi(n2);
-    (SafeF64((m.0 / factor1).round() * factor1), SafeF64((v.0 / factor2).round() * factor2))
+// inverse of apply_scaling
+pub(crate) fn invert_scaling<G: Scope>(data: &Collection<G, (usize, (usize, RowValue))>, mean: &Collection<G, (usize, (SafeF64, SafeF64))>) -> Collection<G, (usize, RowValue)>
+where G::Timestamp: Lattice+Ord {
+    broadcast_join(data, mean)
+        .map(|(_key, ((ix, val), (mean, var)))| (ix, RowValue::Float(val.get_float() * var.0 + mean.0)))
 }
 
 
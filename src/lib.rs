@@ -1,3 +1,4 @@
 pub mod feature_encoders;
 pub mod types; // Ensure this line is present
-pub mod pipelines;
\ No newline at end of file
+pub mod pipelines;
+pub mod operators;
\ No newline at end of file
@@ -1,6 +1,7 @@
 mod types;
 mod feature_encoders;
 mod pipelines;
+mod operators;
 
 
 extern crate timely;
@@ -16,7 +17,10 @@ use timely::worker::Worker;
 use IncrementalFeatureEncoders::types::row::Row;
 use IncrementalFeatureEncoders::feature_encoders::column_encoder::{*};
 use IncrementalFeatureEncoders::feature_encoders::one_hot_encoder::OneHotEncoder;
-use IncrementalFeatureEncoders::feature_encoders::multi_column_encoder::multi_column_encoder;
+use IncrementalFeatureEncoders::feature_encoders::multi_column_encoder::{multi_column_encoder, ColumnTransformer};
+use IncrementalFeatureEncoders::feature_encoders::row_encoder::RowEncoder;
+use IncrementalFeatureEncoders::feature_encoders::normalizer::Normalizer;
+use IncrementalFeatureEncoders::feature_encoders::pca::Pca;
 use IncrementalFeatureEncoders::feature_encoders::feature_extraction::count_vectorizer::CountVectorizer;
 use IncrementalFeatureEncoders::feature_encoders::ordinal_encoder::OrdinalEncoder;
 use IncrementalFeatureEncoders::feature_encoders::standard_scaler::StandardScaler;
@@ -26,9 +30,9 @@ use IncrementalFeatureEncoders::feature_encoders::kbins_discretizer::KBinsDiscre
 use IncrementalFeatureEncoders::feature_encoders::passthrough::Passthrough;
 use IncrementalFeatureEncoders::feature_encoders::pipeline::Pipeline;
 use IncrementalFeatureEncoders::feature_encoders::polynomial_features_encoder::PolynomialFeaturesEncoder;
-use IncrementalFeatureEncoders::pipelines::diabetes_dataset_reader::read_csv2;
 use IncrementalFeatureEncoders::types::row_value::RowValue;
 use IncrementalFeatureEncoders::pipelines::pipeline_3_diabetes::diabetes;
+use IncrementalFeatureEncoders::operators::matrix_sink::DenseMatrixSink;
 
 const SLEEPING_DURATION: u64 = 250;
 
@@ -37,10 +41,13 @@ fn main() {
     demo_multi_column_encoder(false);
     demo_multi_column_encoder2(false);
     demo_multi_column_encoder3(false);
+    demo_nested_column_transformer(false);
+    demo_materialized_matrix(false);
     text_encoder_demo(false);
     micro_benchmark_standard_scaler();
     micro_benchmark1();
     micro_benchmark_ordinal();
+    micro_benchmark_update_latency();
     diabetes_pipeline();
     demo_presentation();
 }
@@ -241,6 +248,88 @@ fn demo_multi_column_encoder3(quiet: bool) {
     print_demo_separator()
 }
 
+fn demo_nested_column_transformer(quiet: bool) {
+    println!("DEMO NESTED COLUMN TRANSFORMER\n");
+    // Input: Tuple
+    timely::execute_from_args(std::env::args(), move |worker| {
+        let mut input = InputSession::new();
+        worker.dataflow(|scope| {
+            let mut input_df = input.to_collection(scope);
+            if !quiet {
+                input_df = input_df.inspect(|x| println!("IN: {:?}", x));
+            }
+
+            let inner_config: Vec<(usize, Box<dyn ColumnEncoder< _>>)> = vec![
+                (0, Box::new(StandardScaler::new())),
+                (1, Box::new(OneHotEncoder::new())),
+            ];
+            let outer_config: Vec<(usize, Box<dyn ColumnEncoder< _>>)> = vec![
+                (0, Box::new(KBinsDiscretizer::new(3))),
+            ];
+            // column transformer -> pca -> normalizer -> column transformer on the normalized components
+            let mut pipeline = Pipeline::new(vec![
+                Box::new(ColumnTransformer::new(inner_config)),
+                Box::new(Pca::new(2)),
+                Box::new(Normalizer::new()),
+                Box::new(ColumnTransformer::new(outer_config))
+            ]);
+            pipeline.fit_rows(&input_df);
+            pipeline.transform_rows(&input_df)
+                .inspect(|x| println!("OUT: {:?}", x))
+                .probe()
+        });
+
+        input.advance_to(0);
+        for person in 0 .. 10 {
+            let person_int = person as i64;
+            input.insert((person,Row::with_integer_vec(vec![person_int, person_int % 3])));
+        }
+
+    }).expect("Computation terminated abnormally");
+    print_demo_separator()
+}
+
+fn demo_materialized_matrix(quiet: bool) {
+    println!("DEMO MATERIALIZED MATRIX\n");
+    // Input: Tuple
+    timely::execute_from_args(std::env::args(), move |worker| {
+        let mut input = InputSession::new();
+        let mut sink = DenseMatrixSink::new();
+        let probe = worker.dataflow(|scope| {
+            let mut input_df = input.to_collection(scope);
+            if !quiet {
+                input_df = input_df.inspect(|x| println!("IN: {:?}", x));
+            }
+
+            let config: Vec<(usize, Box<dyn ColumnEncoder< _>>)> = vec![
+                (0, Box::new(StandardScaler::new())),
+                (1, Box::new(OneHotEncoder::new())),
+            ];
+            let mut transformer = ColumnTransformer::new(config);
+            transformer.fit_rows(&input_df);
+            // only the changed cells leave the dataflow, the matrix itself lives in the sink
+            sink.attach(&transformer.transform_cells(&input_df))
+                .probe()
+        });
+
+        input.advance_to(0);
+        for person in 0 .. 10 {
+            let person_int = person as i64;
+            input.insert((person,Row::with_integer_vec(vec![person_int, person_int % 3])));
+        }
+        input.advance_to(1);
+        input.flush();
+        worker.step_while(|| probe.less_than(input.time()));
+        sink.advance(&probe);
+        let matrix = sink.matrix();
+        for row in 0..matrix.n_rows {
+            println!("ROW {}: {:?}", row, matrix.row(row));
+        }
+
+    }).expect("Computation terminated abnormally");
+    print_demo_separator()
+}
+
 fn generate_random_string(tokens : Vec<&str>) -> String {
 
     // Create a random number generator
@@ -466,6 +555,62 @@ fn micro_benchmark1() {
     print_demo_separator()
 }
 
+// per-update latency of the whole column transformer against a single encoder. every update changes column 0 of one
+// row: the assembled vectors rebuild and re-send the whole vector of that row, so their latency grows with the number
+// of columns, the cells only carry the changed segment
+fn micro_benchmark_update_latency() {
+    println!("MICRO BENCHMARK UPDATE LATENCY\n");
+    let size = std::env::args().nth(1).and_then(|s| s.parse::<usize>().ok()).unwrap_or(10000);
+    let updates = std::env::args().nth(2).and_then(|s| s.parse::<usize>().ok()).unwrap_or(100);
+
+    for (cols, cells) in [1, 5, 10].iter().flat_map(|&cols| [(cols, false), (cols, true)]) {
+        timely::execute_from_args(std::env::args(), move |worker| {
+            let mut input = InputSession::new();
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let config: Vec<(usize, Box<dyn ColumnEncoder< _>>)> = (0..cols)
+                    .map(|col_id| (col_id, Box::new(StandardScaler::new_with_rounding(-2, 0)) as Box<dyn ColumnEncoder<_>>))
+                    .collect();
+                let mut transformer = ColumnTransformer::new(config);
+                transformer.fit_rows(&input_df);
+
+                if cells {
+                    transformer.transform_cells(&input_df).probe()
+                } else {
+                    transformer.transform_vectors(&input_df).probe()
+                }
+            });
+
+            input.advance_to(0);
+            let mut person = worker.index();
+            while person < size {
+                let rv = RowValue::Integer((person % 10) as i64);
+                input.insert((person, Row::with_row_values(vec![rv; cols])));
+                person += worker.peers();
+            }
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // every worker updates the rows it inserted
+            let timer_updates = Instant::now();
+            for (i, person) in (worker.index()..size).step_by(worker.peers()).take(updates).enumerate() {
+                let mut old = vec![RowValue::Integer((person % 10) as i64); cols];
+                input.remove((person, Row::with_row_values(old.clone())));
+                old[0] = RowValue::Integer(((person + 1) % 10) as i64);
+                input.insert((person, Row::with_row_values(old)));
+                input.advance_to(2 + i);
+                input.flush();
+                worker.step_while(|| probe.less_than(input.time()));
+            }
+            let per_update = timer_updates.elapsed() / updates.max(1) as u32;
+            let assembly = if cells { "cells" } else { "vectors" };
+            println!("{} columns, {}: {:?} per update", cols, assembly, per_update);
+        }).expect("Computation terminated abnormally");
+    }
+    print_demo_separator()
+}
+
 fn diabetes_pipeline() {
     println!("DIABETES PIPELINE\n");
     let mut r1 = std::env::args().nth(1).and_then(|s| s.parse::<i32>().ok()).unwrap_or(2);
@@ -479,10 +624,10 @@ fn diabetes_pipeline() {
     if minus2 == 1 {
         r2 = -r2
     }
-    let rows = read_csv2("data/5050_split.csv");
-    match rows {
+    // the workers open the file themselves, a missing file is reported here instead of panicking in every worker
+    match std::fs::metadata("data/5050_split.csv") {
         Err(err) => eprintln!("Error reading CSV: {}", err),
-        Ok(rows) => diabetes(rows, r1, r2, init_size),
+        Ok(_) => diabetes("data/5050_split.csv", r1, r2, init_size),
     }
 }
 
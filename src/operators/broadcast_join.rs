@@ -0,0 +1,135 @@
+use differential_dataflow::{AsCollection, Collection, ExchangeData};
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::arrange::{Arranged, TraceAgent};
+use differential_dataflow::operators::arrange::arrangement::arrange_core;
+use differential_dataflow::trace::implementations::{ValBatcher, ValBuilder, ValSpine};
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::Broadcast;
+use timely::dataflow::Scope;
+
+// Joins data with a small collection of fitted metadata (means, vocabularies, ...) without moving the data.
+// The metadata is broadcast, so every worker holds a full copy, and both sides are arranged with the Pipeline pact,
+// so each worker joins its own rows. A keyed join would instead send all rows of a constant key to one worker.
+pub fn broadcast_join<G: Scope, K, V1, V2>(data: &Collection<G, (K, V1)>, meta: &Collection<G, (K, V2)>) -> Collection<G, (K, (V1, V2))>
+where
+    G::Timestamp: Lattice+Ord,
+    K: ExchangeData,
+    V1: ExchangeData,
+    V2: ExchangeData,
+{
+    let data = arrange_local(data, "BroadcastJoinData");
+    let meta = arrange_local(&broadcast(meta), "BroadcastJoinMeta");
+    data.join_core(&meta, |key, val1, val2| Some((key.clone(), (val1.clone(), val2.clone()))))
+}
+
+// the records of data whose key is not in keys (keys must be distinct), the broadcast counterpart of antijoin
+pub fn broadcast_antijoin<G: Scope, K, V>(data: &Collection<G, (K, V)>, keys: &Collection<G, K>) -> Collection<G, (K, V)>
+where
+    G::Timestamp: Lattice+Ord,
+    K: ExchangeData,
+    V: ExchangeData,
+{
+    let arranged = arrange_local(data, "BroadcastAntijoinData");
+    let keys = arrange_local(&broadcast(keys).map(|key| (key, ())), "BroadcastAntijoinKeys");
+    let matched = arranged.join_core(&keys, |key, val, _| Some((key.clone(), val.clone())));
+    data.concat(&matched.negate())
+}
+
+// a full copy of the collection on every worker
+fn broadcast<G: Scope, D: ExchangeData>(collection: &Collection<G, D>) -> Collection<G, D>
+where
+    G::Timestamp: Lattice+Ord,
+{
+    collection.inner.broadcast().as_collection()
+}
+
+// arranges the collection on the worker that holds the records
+fn arrange_local<G: Scope, K, V>(data: &Collection<G, (K, V)>, name: &str) -> Arranged<G, TraceAgent<ValSpine<K, V, G::Timestamp, isize>>>
+where
+    G::Timestamp: Lattice+Ord,
+    K: ExchangeData,
+    V: ExchangeData,
+{
+    arrange_core::<_, _, ValBatcher<K, V, G::Timestamp, isize>, ValBuilder<K, V, G::Timestamp, isize>, ValSpine<K, V, G::Timestamp, isize>>(
+        &data.inner, Pipeline, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use super::*;
+    #[test]
+    fn broadcast_join_keeps_rows_on_their_worker() {
+        let joined = Arc::new(Mutex::new(Vec::new()));
+        let unmatched = Arc::new(Mutex::new(Vec::new()));
+        let joined_clone = Arc::clone(&joined); // Clone Arc for use inside closure
+        let unmatched_clone = Arc::clone(&unmatched);
+        let result = timely::execute(timely::Config::process(3), move |worker| {
+            let mut data = InputSession::new();
+            let mut meta = InputSession::new();
+            let index = worker.index();
+            let joined = Arc::clone(&joined_clone);
+            let unmatched = Arc::clone(&unmatched_clone);
+            let probe = worker.dataflow(|scope| {
+                let data_df = data.to_collection(scope);
+                let meta_df = meta.to_collection(scope);
+                broadcast_antijoin(&data_df, &meta_df.map(|(key, _)| key))
+                    .inspect(move |((key, row), time, diff)| unmatched.lock().unwrap().push((index, *time, *key, *row, *diff)));
+                broadcast_join(&data_df, &meta_df)
+                    .inspect(move |((key, (row, m)), time, diff)| joined.lock().unwrap().push((index, *time, *key, *row, *m, *diff)))
+                    .probe()
+            });
+
+            // every worker holds its own rows, the metadata only comes from worker 0
+            data.advance_to(0);
+            meta.advance_to(0);
+            for key in 0 .. 2 {
+                data.insert((key, 10 * index + key));
+            }
+            if index == 0 {
+                meta.insert((0, 100));
+            }
+            data.advance_to(1);
+            meta.advance_to(1);
+            data.flush();
+            meta.flush();
+            worker.step_while(|| probe.less_than(data.time()));
+
+            // a metadata update reaches the rows of every worker
+            if index == 0 {
+                meta.remove((0, 100));
+                meta.insert((1, 200));
+            }
+            data.advance_to(2);
+            meta.advance_to(2);
+            data.flush();
+            meta.flush();
+            worker.step_while(|| probe.less_than(data.time()));
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+
+        let mut joined = joined.lock().unwrap().clone();
+        joined.sort();
+        let mut expected = Vec::new();
+        for index in 0 .. 3 {
+            expected.push((index, 0, 0, 10 * index, 100, 1));
+            expected.push((index, 1, 0, 10 * index, 100, -1));
+            expected.push((index, 1, 1, 10 * index + 1, 200, 1));
+        }
+        expected.sort();
+        assert_eq!(joined, expected, "Joined output is incorrect");
+
+        // accumulated per (worker, key, row), the unmatched rows end up being the rows of key 0
+        let mut counts: BTreeMap<(usize, usize, usize), isize> = BTreeMap::new();
+        for (index, _time, key, row, diff) in unmatched.lock().unwrap().iter() {
+            *counts.entry((*index, *key, *row)).or_insert(0) += diff;
+        }
+        let unmatched_rows: Vec<(usize, usize, usize)> = counts.into_iter()
+            .filter(|(_, count)| *count != 0)
+            .map(|(row, _)| row)
+            .collect();
+        assert_eq!(unmatched_rows, (0 .. 3).map(|index| (index, 0, 10 * index)).collect::<Vec<_>>(), "Unmatched output is incorrect");
+    }
+}
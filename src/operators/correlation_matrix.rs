@@ -0,0 +1,175 @@
+use differential_dataflow::Collection;
+use differential_dataflow::difference::{Abelian, IsZero, Monoid, Semigroup};
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::{Count, Threshold};
+use serde::{Deserialize, Serialize};
+use timely::dataflow::Scope;
+use crate::types::row::Row;
+use crate::types::row_value::RowValue;
+use crate::types::safe_f64::SafeF64;
+
+// count, means and (co-)moments of two numeric columns
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) struct CoMomentAggregate {
+    mean_x: SafeF64,
+    mean_y: SafeF64,
+    m2_x: SafeF64,
+    m2_y: SafeF64,
+    c_xy: SafeF64,
+    count: isize,
+}
+
+impl CoMomentAggregate {
+    pub(crate) fn new(x: f64, y: f64, multiplicity: isize) -> Self {
+        Self { mean_x: SafeF64(x), mean_y: SafeF64(y), m2_x: SafeF64(0.0), m2_y: SafeF64(0.0), c_xy: SafeF64(0.0), count: multiplicity }
+    }
+
+    // pearson correlation coefficient
+    pub(crate) fn correlation(&self) -> f64 {
+        let denominator = (self.m2_x.0 * self.m2_y.0).sqrt();
+        if denominator <= 0.0 { 0.0 } else { self.c_xy.0 / denominator }
+    }
+}
+
+impl IsZero for CoMomentAggregate {
+    fn is_zero(&self) -> bool {
+        self.count == 0
+    }
+}
+
+impl Semigroup for CoMomentAggregate {
+    fn plus_equals(&mut self, other: &Self) {
+        let c1 = self.count as f64;
+        let c2 = other.count as f64;
+        self.count += other.count;
+        if self.count == 0 {
+            *self = Self::zero();
+            return;
+        }
+        let c_new = self.count as f64;
+        let delta_x = self.mean_x.0 - other.mean_x.0;
+        let delta_y = self.mean_y.0 - other.mean_y.0;
+        self.mean_x = SafeF64((self.mean_x.0*c1 + other.mean_x.0*c2) / c_new);
+        self.mean_y = SafeF64((self.mean_y.0*c1 + other.mean_y.0*c2) / c_new);
+        self.m2_x = SafeF64(self.m2_x.0 + other.m2_x.0 + (delta_x*delta_x)*c1*c2/c_new);
+        self.m2_y = SafeF64(self.m2_y.0 + other.m2_y.0 + (delta_y*delta_y)*c1*c2/c_new);
+        self.c_xy = SafeF64(self.c_xy.0 + other.c_xy.0 + (delta_x*delta_y)*c1*c2/c_new);
+    }
+}
+
+impl Monoid for CoMomentAggregate {
+    fn zero() -> Self {
+        Self { mean_x: SafeF64(0.0), mean_y: SafeF64(0.0), m2_x: SafeF64(0.0), m2_y: SafeF64(0.0), c_xy: SafeF64(0.0), count: 0 }
+    }
+}
+
+impl Abelian for CoMomentAggregate {
+    fn negate(&mut self) {
+        self.m2_x = SafeF64(self.m2_x.0 * -1.0);
+        self.m2_y = SafeF64(self.m2_y.0 * -1.0);
+        self.c_xy = SafeF64(self.c_xy.0 * -1.0);
+        self.count *= -1;
+    }
+}
+
+// Maintains the pearson correlation of every pair of numerical columns (text columns are skipped).
+// Each cell ((i, j), r) is emitted in both orientations and the diagonal is always 1, so a new row
+// only retracts and re-inserts the cells whose correlation actually changed.
+pub fn correlation_matrix<G: Scope>(data: &Collection<G, (usize, Row)>) -> Collection<G, ((usize, usize), SafeF64)>
+where G::Timestamp: Lattice+Ord {
+    let upper = co_moments(&data.flat_map(|(ix, row)| {
+        let numerical = numerical_values(&row);
+        let mut pairs = Vec::with_capacity(numerical.len() * numerical.len() / 2);
+        for a in 0..numerical.len() {
+            for b in (a + 1)..numerical.len() {
+                let ((i, x), (j, y)) = (numerical[a], numerical[b]);
+                pairs.push(((i, j), (ix, SafeF64(x), SafeF64(y))));
+            }
+        }
+        pairs
+    }));
+    let diagonal = data
+        .flat_map(|(_ix, row)| numerical_values(&row).into_iter().map(|(i, _x)| i))
+        .distinct()
+        .map(|i| ((i, i), SafeF64(1.0)));
+
+    upper
+        .flat_map(|((i, j), r)| vec![((i, j), r), ((j, i), r)])
+        .concat(&diagonal)
+}
+
+// pearson correlation of the given (column, column) pairs only
+pub fn correlations<G: Scope>(data: &Collection<G, (usize, Row)>, pairs: Vec<(usize, usize)>) -> Collection<G, ((usize, usize), SafeF64)>
+where G::Timestamp: Lattice+Ord {
+    co_moments(&data.flat_map(move |(ix, row)| pairs.iter()
+        .map(|&(i, j)| ((i, j), (ix, SafeF64(row.values[i].get_float()), SafeF64(row.values[j].get_float()))))
+        .collect::<Vec<_>>()))
+}
+
+fn co_moments<G: Scope>(pairs: &Collection<G, ((usize, usize), (usize, SafeF64, SafeF64))>) -> Collection<G, ((usize, usize), SafeF64)>
+where G::Timestamp: Lattice+Ord {
+    pairs
+        .threshold(|(_cell, (_ix, x, y)), c| CoMomentAggregate::new(x.0, y.0, *c))
+        .map(|(cell, _value)| cell)
+        .count()
+        .map(|(cell, agg)| (cell, SafeF64(agg.correlation())))
+}
+
+fn numerical_values(row: &Row) -> Vec<(usize, f64)> {
+    row.values.iter()
+        .enumerate()
+        .filter_map(|(i, v)| match v {
+            RowValue::Integer(_) | RowValue::Float(_) => Some((i, v.get_float())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use super::*;
+    #[test]
+    fn correlation_matrix_works() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+
+                correlation_matrix(&input_df)
+                    .inspect(move |((cell, r),_,_)| {
+                        let mut out = output_clone.lock().unwrap();
+                        out.push((*cell, r.0));
+                    })
+                    .probe()
+            });
+
+            input.advance_to(0);
+            for person in 0 .. 10 {
+                let x = person as f64;
+                input.insert((person, Row::with_row_values(vec![
+                    RowValue::Float(x), RowValue::Float(2.0 * x), RowValue::Float(-x), RowValue::Text(x.to_string())])));
+            }
+
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // lock the Mutex to access data
+            let mut output = output.lock().unwrap();
+            output.sort_by_key(|(cell, _)| *cell);
+
+            // Check the output
+            let sign = |i: usize| if i == 2 { -1.0 } else { 1.0 };
+            let expected_cells: Vec<(usize, usize)> = (0..3).flat_map(|i| (0..3).map(move |j| (i, j))).collect();
+            assert_eq!(output.iter().map(|(cell, _)| *cell).collect::<Vec<_>>(), expected_cells, "Matrix cells are incorrect");
+            for ((i, j), r) in output.iter() {
+                assert!((r - sign(*i) * sign(*j)).abs() < 1e-9, "Correlation of {:?} is incorrect: {}", (i, j), r);
+            }
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+}
@@ -0,0 +1,286 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use differential_dataflow::{AsCollection, Collection};
+use differential_dataflow::lattice::Lattice;
+use timely::dataflow::{ProbeHandle, Scope};
+use timely::dataflow::operators::Exchange;
+use timely::progress::Timestamp;
+use crate::types::safe_f64::SafeF64;
+
+// Keeps the numeric feature matrix outside of differential dataflow. The sinks consume the (row id, (position, value))
+// cell updates of ColumnTransformer::transform_cells and apply them in place: a cell is set to its inserted value and
+// cleared when its value is retracted without a replacement. Updates are buffered until their time is complete, so the
+// matrix always reflects a completed timestamp. The cells are gathered on worker 0, the sinks of the other workers stay empty.
+
+// cell updates that arrived but whose time is not complete yet
+struct PendingCells<T> {
+    updates: Rc<RefCell<Vec<(T, usize, usize, SafeF64, isize)>>>,
+}
+
+impl<T: Timestamp> PendingCells<T> {
+    fn new() -> Self {
+        Self { updates: Rc::new(RefCell::new(Vec::new())) }
+    }
+
+    fn attach<G: Scope<Timestamp=T>>(&self, cells: &Collection<G, (usize, (usize, SafeF64))>) -> Collection<G, (usize, (usize, SafeF64))>
+    where T: Lattice+Ord {
+        let updates = Rc::clone(&self.updates);
+        cells.inner
+            .exchange(|_update| 0)
+            .as_collection()
+            .inspect(move |((row, (col, value)), time, diff)| {
+                updates.borrow_mut().push((time.clone(), *row, *col, *value, *diff));
+            })
+    }
+
+    // removes the updates of all times the probe has passed and returns the new value of every touched cell,
+    // None for a cell whose value was retracted without a replacement
+    fn drain_complete(&self, probe: &ProbeHandle<T>) -> Vec<(usize, usize, Option<f64>)> {
+        let mut updates = self.updates.borrow_mut();
+        let (complete, pending): (Vec<_>, Vec<_>) = updates.drain(..)
+            .partition(|(time, _, _, _, _)| !probe.less_equal(time));
+        *updates = pending;
+
+        // the net change of every value of a cell, a cell holds a single value at a time
+        let mut changes: BTreeMap<(usize, usize), BTreeMap<SafeF64, isize>> = BTreeMap::new();
+        for (_time, row, col, value, diff) in complete {
+            *changes.entry((row, col)).or_insert_with(BTreeMap::new).entry(value).or_insert(0) += diff;
+        }
+        changes.into_iter()
+            .filter(|(_cell, values)| values.values().any(|diff| *diff != 0))
+            .map(|((row, col), values)| {
+                let value = values.into_iter().find(|(_value, diff)| *diff > 0).map(|(value, _diff)| value.0);
+                (row, col, value)
+            })
+            .collect()
+    }
+}
+
+// dense row-major matrix, row i starts at values[i * stride]. the columns from n_cols to stride are spare
+// capacity for new columns, snapshots are compact (stride == n_cols)
+#[derive(Debug, Clone, PartialEq)]
+pub struct DenseMatrix {
+    pub n_rows: usize,
+    pub n_cols: usize,
+    values: Vec<f64>,
+    stride: usize,
+}
+
+impl DenseMatrix {
+    fn new() -> Self {
+        Self { n_rows: 0, n_cols: 0, values: Vec::new(), stride: 0 }
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        if row < self.n_rows && col < self.n_cols { self.values[row * self.stride + col] } else { 0.0 }
+    }
+
+    pub fn row(&self, row: usize) -> &[f64] {
+        &self.values[row * self.stride..row * self.stride + self.n_cols]
+    }
+
+    // the values in row-major order without the spare columns
+    pub fn to_vec(&self) -> Vec<f64> {
+        (0..self.n_rows).flat_map(|row| self.row(row).iter().copied()).collect()
+    }
+
+    fn compact(&self) -> DenseMatrix {
+        DenseMatrix { n_rows: self.n_rows, n_cols: self.n_cols, values: self.to_vec(), stride: self.n_cols }
+    }
+
+    fn set(&mut self, row: usize, col: usize, value: f64) {
+        if col >= self.stride {
+            // a wider vector (e.g. a new category) moves every row, so the buffer is laid out again. the stride
+            // at least doubles, so a growing vocabulary only triggers a logarithmic number of layouts
+            let stride = (col + 1).max(self.stride * 2);
+            let mut values = vec![0f64; self.n_rows * stride];
+            for i in 0..self.n_rows {
+                values[i * stride..i * stride + self.n_cols].copy_from_slice(self.row(i));
+            }
+            self.values = values;
+            self.stride = stride;
+        }
+        self.n_cols = self.n_cols.max(col + 1);
+        if row >= self.n_rows {
+            self.n_rows = row + 1;
+            self.values.resize(self.n_rows * self.stride, 0.0);
+        }
+        self.values[row * self.stride + col] = value;
+    }
+}
+
+pub struct DenseMatrixSink<T> {
+    pending: PendingCells<T>,
+    matrix: DenseMatrix,
+}
+
+impl<T: Timestamp+Lattice+Ord> DenseMatrixSink<T> {
+    pub fn new() -> Self {
+        Self { pending: PendingCells::new(), matrix: DenseMatrix::new() }
+    }
+
+    // buffers the cell updates of the collection, probe the returned collection and call advance
+    pub fn attach<G: Scope<Timestamp=T>>(&self, cells: &Collection<G, (usize, (usize, SafeF64))>) -> Collection<G, (usize, (usize, SafeF64))> {
+        self.pending.attach(cells)
+    }
+
+    // applies the updates of all completed times in place
+    pub fn advance(&mut self, probe: &ProbeHandle<T>) {
+        for (row, col, value) in self.pending.drain_complete(probe) {
+            match value {
+                Some(value) => self.matrix.set(row, col, value),
+                None if row < self.matrix.n_rows && col < self.matrix.n_cols => self.matrix.set(row, col, 0.0),
+                None => {}
+            }
+        }
+    }
+
+    pub fn matrix(&self) -> &DenseMatrix {
+        &self.matrix
+    }
+
+    pub fn snapshot(&self) -> DenseMatrix {
+        self.matrix.compact()
+    }
+}
+
+// compressed sparse rows, the non-zero cells of row i are indices/values[indptr[i]..indptr[i + 1]]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsrMatrix {
+    pub n_rows: usize,
+    pub n_cols: usize,
+    pub indptr: Vec<usize>,
+    pub indices: Vec<usize>,
+    pub values: Vec<f64>,
+}
+
+pub struct CsrMatrixSink<T> {
+    pending: PendingCells<T>,
+    // non-zero cells per row, kept sorted by position
+    rows: Vec<BTreeMap<usize, f64>>,
+    n_cols: usize,
+}
+
+impl<T: Timestamp+Lattice+Ord> CsrMatrixSink<T> {
+    pub fn new() -> Self {
+        Self { pending: PendingCells::new(), rows: Vec::new(), n_cols: 0 }
+    }
+
+    pub fn attach<G: Scope<Timestamp=T>>(&self, cells: &Collection<G, (usize, (usize, SafeF64))>) -> Collection<G, (usize, (usize, SafeF64))> {
+        self.pending.attach(cells)
+    }
+
+    pub fn advance(&mut self, probe: &ProbeHandle<T>) {
+        for (row, col, value) in self.pending.drain_complete(probe) {
+            match value {
+                Some(value) => {
+                    if row >= self.rows.len() {
+                        self.rows.resize(row + 1, BTreeMap::new());
+                    }
+                    self.n_cols = self.n_cols.max(col + 1);
+                    self.rows[row].insert(col, value);
+                }
+                None => {
+                    if let Some(cells) = self.rows.get_mut(row) {
+                        cells.remove(&col);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> CsrMatrix {
+        let mut indptr = Vec::with_capacity(self.rows.len() + 1);
+        let mut indices = Vec::new();
+        let mut values = Vec::new();
+        indptr.push(0);
+        for row in self.rows.iter() {
+            for (col, value) in row.iter() {
+                indices.push(*col);
+                values.push(*value);
+            }
+            indptr.push(indices.len());
+        }
+        CsrMatrix { n_rows: self.rows.len(), n_cols: self.n_cols, indptr, indices, values }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use differential_dataflow::input::InputSession;
+    use super::*;
+    #[test]
+    fn matrix_sinks_apply_cell_updates() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let mut dense = DenseMatrixSink::new();
+            let mut csr = CsrMatrixSink::new();
+            let probe = worker.dataflow(|scope| {
+                let cells = input.to_collection(scope);
+                csr.attach(&dense.attach(&cells)).probe()
+            });
+
+            input.advance_to(0);
+            input.insert((0, (0, SafeF64(1.0))));
+            input.insert((1, (1, SafeF64(2.0))));
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+            dense.advance(&probe);
+            csr.advance(&probe);
+            assert_eq!(dense.snapshot().to_vec(), vec![1.0, 0.0, 0.0, 2.0]);
+
+            // update cell (1, 1) and add a new column, time 1 is not complete until the input advances
+            input.remove((1, (1, SafeF64(2.0))));
+            input.insert((1, (1, SafeF64(3.0))));
+            input.insert((0, (2, SafeF64(4.0))));
+            input.flush();
+            worker.step();
+            dense.advance(&probe);
+            assert_eq!(dense.snapshot().to_vec(), vec![1.0, 0.0, 0.0, 2.0]);
+
+            input.advance_to(2);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+            dense.advance(&probe);
+            csr.advance(&probe);
+            assert_eq!(dense.snapshot().to_vec(), vec![1.0, 0.0, 4.0, 0.0, 3.0, 0.0]);
+            assert_eq!(csr.snapshot(), CsrMatrix {
+                n_rows: 2, n_cols: 3, indptr: vec![0, 2, 3], indices: vec![0, 2, 1], values: vec![1.0, 4.0, 3.0]
+            });
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+
+    #[test]
+    fn matrix_sinks_gather_cells_on_worker_0() {
+        let result = timely::execute(timely::Config::process(2), move |worker| {
+            let mut input = InputSession::new();
+            let mut csr = CsrMatrixSink::new();
+            let probe = worker.dataflow(|scope| {
+                csr.attach(&input.to_collection(scope)).probe()
+            });
+
+            // every worker sends the cell of its own row
+            let row = worker.index();
+            input.advance_to(0);
+            input.insert((row, (0, SafeF64(0.1 + 0.2))));
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+            csr.advance(&probe);
+            let expected_rows = if worker.index() == 0 { 2 } else { 0 };
+            assert_eq!(csr.snapshot().n_rows, expected_rows, "Cells were not gathered on worker 0");
+
+            // a retracted cell without a replacement is removed, whatever its value
+            input.remove((row, (0, SafeF64(0.1 + 0.2))));
+            input.advance_to(2);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+            csr.advance(&probe);
+            assert!(csr.snapshot().values.is_empty(), "Retracted cells are still there");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+}
@@ -0,0 +1,5 @@
+pub mod correlation_matrix;
+pub mod matrix_sink;
+pub mod parallel_fit;
+pub mod broadcast_join;
+pub mod republish;
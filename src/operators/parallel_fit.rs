@@ -0,0 +1,149 @@
+use std::hash::Hash;
+use differential_dataflow::{Collection, ExchangeData};
+use differential_dataflow::difference::{Abelian, IsZero, Monoid, Semigroup};
+use differential_dataflow::hashable::Hashable;
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::{Count, Reduce, Threshold};
+use timely::dataflow::Scope;
+
+// Fitting in two stages, so the statistics of a column are not aggregated on a single worker.
+// Stage one turns every record into an aggregate (the threshold -> count trick) and sums them per (key, partition),
+// the partitions are spread over the workers. Stage two combines the (at most peers) partial aggregates of every key,
+// so a new row only updates the partial aggregate of its partition and the final sum of peers partials.
+pub fn partitioned_aggregate<G: Scope, K, D, A, P, L>(data: &Collection<G, (K, D)>, partition: P, logic: L) -> Collection<G, (K, A)>
+where
+    G::Timestamp: Lattice+Ord,
+    K: ExchangeData + Hash,
+    D: ExchangeData + Hash,
+    A: Abelian + ExchangeData,
+    P: Fn(&D) -> u64 + 'static,
+    L: Fn(&D, isize) -> A + 'static,
+{
+    let peers = data.scope().peers() as u64;
+    let partials = data
+        .map(move |(key, val)| ((key, partition(&val) % peers), val))
+        .threshold(move |(_key, val), multiplicity| logic(val, *multiplicity))
+        .map(|(key, _val)| key)
+        .count();
+    combine_partials(&partials)
+}
+
+// partitions by row id, for (row id, value) records
+pub fn by_row_id<V>((ix, _val): &(usize, V)) -> u64 {
+    *ix as u64
+}
+
+// partitions by the hash of the record, for records without a row id (e.g. distinct values)
+pub fn by_hash<D: Hash>(val: &D) -> u64 {
+    val.hashed()
+}
+
+// Position assignments can not be combined from partials: summing the partials from zero hands out every position
+// again, so one new value could move all of them. Only the distinct values are found in parallel, their changes reach
+// one final stage that accumulates them in arrival order (the threshold -> count trick), so a value keeps its position
+// until it is retracted.
+// This final stage runs on a single worker per key, so it does not scale with the number of workers. It only sees a
+// value when it first shows up or disappears, not every row, so it stays small as long as the number of distinct
+// values is small compared to the number of rows. run_parallel_fit_benchmark times the one-hot fits on their own.
+pub fn assign_positions<G: Scope, K, V, A, L>(values: &Collection<G, (K, V)>, logic: L) -> Collection<G, (K, A)>
+where
+    G::Timestamp: Lattice+Ord,
+    K: ExchangeData + Hash,
+    V: ExchangeData + Hash,
+    A: Abelian + ExchangeData,
+    L: Fn(&V, isize) -> A + 'static,
+{
+    values
+        .distinct()
+        .threshold(move |(_key, val), multiplicity| logic(val, *multiplicity))
+        .map(|(key, _val)| key)
+        .count()
+}
+
+// stage two of partitioned_aggregate: sums the partial aggregates of every key
+pub fn combine_partials<G: Scope, K, A>(partials: &Collection<G, ((K, u64), A)>) -> Collection<G, (K, A)>
+where
+    G::Timestamp: Lattice+Ord,
+    K: ExchangeData + Hash,
+    A: Abelian + ExchangeData,
+{
+    partials
+        .map(|((key, _partition), agg)| (key, agg))
+        .reduce(|_key, input, output| {
+            let mut total = A::zero();
+            for (agg, count) in input {
+                // equal partial aggregates of different partitions are consolidated into one record,
+                // a negative count retracts the partial
+                let mut partial = agg.clone();
+                if *count < 0 {
+                    partial.negate();
+                }
+                for _ in 0..count.abs() {
+                    total.plus_equals(&partial);
+                }
+            }
+            if !total.is_zero() {
+                output.push((total, 1isize));
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use super::*;
+    #[test]
+    fn partitioned_aggregate_combines_the_partials_of_all_workers() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let output_clone = Arc::clone(&output); // Clone Arc for use inside closure
+        let result = timely::execute(timely::Config::process(4), move |worker| {
+            let mut input = InputSession::new();
+            let output = Arc::clone(&output_clone);
+            let probe = worker.dataflow(|scope| {
+                partitioned_aggregate(&input.to_collection(scope), by_row_id, |(_ix, value): &(usize, isize), c| value * c)
+                    .inspect(move |(((), sum), time, diff)| output.lock().unwrap().push((*time, *sum, *diff)))
+                    .probe()
+            });
+
+            // every worker inserts two rows of 1, so all partitions hold the same partial sum
+            input.advance_to(0);
+            for ix in [worker.index(), worker.index() + worker.peers()] {
+                input.insert(((), (ix, 1isize)));
+            }
+            input.advance_to(1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            if worker.index() == 0 {
+                input.remove(((), (0, 1)));
+            }
+            input.advance_to(2);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            input.remove(((), (worker.index(), 1)));
+            input.remove(((), (worker.index() + worker.peers(), 1)));
+            if worker.index() == 0 {
+                input.insert(((), (0, 1)));
+            }
+            input.advance_to(3);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+
+        // the sum at every time
+        let mut sums: BTreeMap<usize, BTreeMap<isize, isize>> = BTreeMap::new();
+        for (time, sum, diff) in output.lock().unwrap().iter() {
+            for t in *time .. 3 {
+                *sums.entry(t).or_insert_with(BTreeMap::new).entry(*sum).or_insert(0) += diff;
+            }
+        }
+        let sums: Vec<(usize, Vec<isize>)> = sums.into_iter()
+            .map(|(time, sums)| (time, sums.into_iter().filter(|(_, count)| *count != 0).map(|(sum, _)| sum).collect()))
+            .collect();
+        assert_eq!(sums, vec![(0, vec![8]), (1, vec![7]), (2, vec![])], "Aggregates are incorrect");
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use differential_dataflow::{AsCollection, Collection, ExchangeData};
+use differential_dataflow::hashable::Hashable;
+use differential_dataflow::lattice::Lattice;
+use serde::{Deserialize, Serialize};
+use timely::container::CapacityContainerBuilder;
+use timely::dataflow::channels::pact::Exchange;
+use timely::dataflow::operators::{Capability, Operator};
+use timely::dataflow::Scope;
+use timely::order::PartialOrder;
+use crate::types::safe_f64::SafeF64;
+
+// When fitted parameters are republished. Every published change rewrites all rows that depend on the parameters,
+// so tiny drifts of a statistic can be held back until they matter.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RepublishPolicy {
+    // every change is published
+    Always,
+    // once a parameter moved more than the given amount away from its published value
+    Absolute(f64),
+    // once a parameter moved more than the given fraction of its published value
+    Relative(f64),
+    // the parameters are published rounded to 10^n, one n per parameter (the last one is used for the remaining
+    // parameters), so a change only shows up once a rounded value changes
+    DecimalRounding(Vec<i32>),
+    // every n-th change
+    EveryN(usize),
+}
+
+impl RepublishPolicy {
+    fn publish_value(&self, params: &Vec<SafeF64>) -> Vec<SafeF64> {
+        match self {
+            RepublishPolicy::DecimalRounding(decimals) => params.iter().enumerate()
+                .map(|(i, x)| {
+                    let n = decimals.get(i).or(decimals.last()).copied().unwrap_or(0);
+                    let factor = 10f64.powi(n);
+                    SafeF64((x.0 / factor).round() * factor)
+                })
+                .collect(),
+            _ => params.clone(),
+        }
+    }
+
+    // whether the current parameters replace the published ones, changes counts the changes since the last publish
+    fn triggers(&self, published: &Vec<SafeF64>, current: &Vec<SafeF64>, changes: usize) -> bool {
+        if published.len() != current.len() {
+            return true;
+        }
+        let mut drift = published.iter().zip(current.iter());
+        match self {
+            RepublishPolicy::Always => true,
+            RepublishPolicy::Absolute(max_drift) => drift.any(|(p, c)| (c.0 - p.0).abs() > *max_drift),
+            RepublishPolicy::Relative(max_drift) => drift.any(|(p, c)| (c.0 - p.0).abs() > *max_drift * p.0.abs()),
+            RepublishPolicy::DecimalRounding(_) => self.publish_value(current) != *published,
+            RepublishPolicy::EveryN(n) => changes >= *n,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+enum Republished {
+    Params(Vec<SafeF64>),
+    Staleness(usize),
+}
+
+#[derive(Default)]
+struct KeyState {
+    current: Option<Vec<SafeF64>>,
+    published: Option<Vec<SafeF64>>,
+    // changes of the current parameters that are not published yet
+    changes: usize,
+    staleness: Option<usize>,
+}
+
+// Publishes the parameters of every key according to the policy. Complete times are processed in order, so the
+// published parameters only change at times where the policy triggered. The second collection is the staleness
+// of every key, the number of parameter changes that are held back.
+pub fn republish<G: Scope, K>(params: &Collection<G, (K, Vec<SafeF64>)>, policy: RepublishPolicy)
+    -> (Collection<G, (K, Vec<SafeF64>)>, Collection<G, (K, usize)>)
+where
+    G::Timestamp: Lattice+Ord,
+    K: ExchangeData + Hash,
+{
+    let exchange = Exchange::new(|((key, _params), _time, _diff): &((K, Vec<SafeF64>), G::Timestamp, isize)| key.hashed());
+    let updates = params.inner.unary_frontier::<CapacityContainerBuilder<Vec<_>>, _, _, _>(exchange, "Republish", move |_capability, _info| {
+        let mut pending: Vec<((K, Vec<SafeF64>), G::Timestamp, isize)> = Vec::new();
+        let mut capabilities: Vec<Capability<G::Timestamp>> = Vec::new();
+        let mut states: HashMap<K, KeyState> = HashMap::new();
+
+        move |input, output| {
+            input.for_each(|capability, data| {
+                capabilities.push(capability.retain());
+                pending.extend(data.drain(..));
+            });
+
+            // the times the input frontier has passed are complete
+            let mut complete: Vec<G::Timestamp> = pending.iter()
+                .map(|(_update, time, _diff)| time.clone())
+                .filter(|time| !input.frontier().less_equal(time))
+                .collect();
+            complete.sort();
+            complete.dedup();
+
+            for time in complete {
+                let (updates, rest): (Vec<_>, Vec<_>) = pending.drain(..).partition(|(_update, t, _diff)| *t == time);
+                pending = rest;
+                let capability = capabilities.iter()
+                    .find(|capability| capability.time().less_equal(&time))
+                    .expect("no capability for pending update")
+                    .delayed(&time);
+                let mut session = output.session(&capability);
+
+                let mut changes_per_key: HashMap<K, Vec<(Vec<SafeF64>, isize)>> = HashMap::new();
+                for ((key, params), _time, diff) in updates {
+                    changes_per_key.entry(key).or_insert_with(Vec::new).push((params, diff));
+                }
+                for (key, changes) in changes_per_key {
+                    let state = states.entry(key.clone()).or_insert_with(KeyState::default);
+                    // the retraction of the old and the insertion of the new parameters arrive at the same time
+                    for (params, _diff) in changes.iter().filter(|(_params, diff)| *diff < 0) {
+                        if state.current.as_ref() == Some(params) {
+                            state.current = None;
+                        }
+                    }
+                    for (params, _diff) in changes.into_iter().filter(|(_params, diff)| *diff > 0) {
+                        state.current = Some(params);
+                    }
+                    state.changes += 1;
+
+                    let (next, triggered) = match (&state.current, &state.published) {
+                        (None, _) => (None, true),
+                        (Some(current), Some(published)) if !policy.triggers(published, current, state.changes) =>
+                            (Some(published.clone()), false),
+                        (Some(current), _) => (Some(policy.publish_value(current)), true),
+                    };
+                    if next != state.published {
+                        if let Some(old) = state.published.take() {
+                            session.give(((key.clone(), Republished::Params(old)), time.clone(), -1isize));
+                        }
+                        if let Some(new) = next.clone() {
+                            session.give(((key.clone(), Republished::Params(new)), time.clone(), 1isize));
+                        }
+                        state.published = next;
+                    }
+                    if triggered {
+                        state.changes = 0;
+                    }
+
+                    let staleness = state.current.as_ref().map(|_| state.changes);
+                    if staleness != state.staleness {
+                        if let Some(old) = state.staleness {
+                            session.give(((key.clone(), Republished::Staleness(old)), time.clone(), -1isize));
+                        }
+                        if let Some(new) = staleness {
+                            session.give(((key.clone(), Republished::Staleness(new)), time.clone(), 1isize));
+                        }
+                        state.staleness = staleness;
+                    }
+                }
+            }
+
+            capabilities.retain(|capability| pending.iter().any(|(_update, time, _diff)| capability.time().less_equal(time)));
+        }
+    }).as_collection();
+
+    let published = updates.flat_map(|(key, update)| match update {
+        Republished::Params(params) => Some((key, params)),
+        Republished::Staleness(_) => None,
+    });
+    let staleness = updates.flat_map(|(key, update)| match update {
+        Republished::Staleness(staleness) => Some((key, staleness)),
+        Republished::Params(_) => None,
+    });
+    (published, staleness)
+}
+
+// republish for parameter pairs like (mean, variance) or (min, range)
+pub fn republish_pairs<G: Scope, K>(params: &Collection<G, (K, (SafeF64, SafeF64))>, policy: RepublishPolicy)
+    -> (Collection<G, (K, (SafeF64, SafeF64))>, Collection<G, (K, usize)>)
+where
+    G::Timestamp: Lattice+Ord,
+    K: ExchangeData + Hash,
+{
+    let (published, staleness) = republish(&params.map(|(key, (a, b))| (key, vec![a, b])), policy);
+    (published.map(|(key, params)| (key, (params[0], params[1]))), staleness)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use differential_dataflow::input::InputSession;
+    use super::*;
+
+    #[test]
+    fn absolute_policy_holds_back_small_drift() {
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input = InputSession::new();
+            let published = Arc::new(Mutex::new(Vec::new()));
+            let staleness = Arc::new(Mutex::new(Vec::new()));
+            let probe = worker.dataflow(|scope| {
+                let params = input.to_collection(scope);
+                let (p, s) = republish_pairs(&params, RepublishPolicy::Absolute(0.1));
+                let published_clone = Arc::clone(&published);
+                let staleness_clone = Arc::clone(&staleness);
+                p.inspect(move |((_, (a, _)), time, diff)| published_clone.lock().unwrap().push((a.0, *time, *diff)));
+                s.inspect(move |((_, n), time, diff)| staleness_clone.lock().unwrap().push((*n, *time, *diff)))
+                    .probe()
+            });
+
+            input.advance_to(0);
+            input.insert((0usize, (SafeF64(1.0), SafeF64(1.0))));
+            input.advance_to(1);
+            input.remove((0usize, (SafeF64(1.0), SafeF64(1.0))));
+            input.insert((0usize, (SafeF64(1.05), SafeF64(1.0))));
+            input.advance_to(2);
+            input.remove((0usize, (SafeF64(1.05), SafeF64(1.0))));
+            input.insert((0usize, (SafeF64(1.2), SafeF64(1.0))));
+            input.advance_to(3);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // the drift at time 1 stays below the tolerance, the one at time 2 does not
+            let mut published = published.lock().unwrap().clone();
+            published.sort_by_key(|&(_, time, diff)| (time, diff));
+            assert_eq!(published, vec![(1.0, 0, 1), (1.0, 2, -1), (1.2, 2, 1)]);
+
+            let mut staleness = staleness.lock().unwrap().clone();
+            staleness.sort_by_key(|&(_, time, diff)| (time, diff));
+            assert_eq!(staleness, vec![(0, 0, 1), (0, 1, -1), (1, 1, 1), (1, 2, -1), (0, 2, 1)]);
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+    }
+}
@@ -1,29 +1,16 @@
 use std::error::Error;
-use csv::Reader;
+use crate::pipelines::csv_reader::{report_malformed, CsvReader, NullPolicy};
 use crate::types::row::Row;
-use crate::types::row_value::RowValue;
+use crate::types::schema::Schema;
 
 pub fn read_adult_csv(file_path: &str) -> Result<Vec<Row>, Box<dyn Error>> {
-    let mut rdr = Reader::from_path(file_path)?;
-    let headers = rdr.headers()?;
-    println!("Headers: {:?}", headers);
+    read_adult_csv_with_schema(file_path).map(|(_schema, rows)| rows)
+}
 
-    let mut rows: Vec<Row> = Vec::new(); // Array to store rows
-    for result in rdr.records() {
-        let record = result?;
-        let row_vals: Vec<RowValue> = record.iter()
-            .map(|s| {
-                let trimmed = s.trim();
-                if let Ok(num) = trimmed.parse::<f64>() {
-                    RowValue::Float(num) // Store as Float if parsable
-                } else {
-                    RowValue::Text(trimmed.to_string()) // Store as Text otherwise
-                }
-            })
-            .collect();
-        let row = Row::with_row_values(row_vals);
-        rows.push(row);
-    }
-
-    Ok(rows)
-}
\ No newline at end of file
+// the unknown categories ("?") stay a category of their own
+pub fn read_adult_csv_with_schema(file_path: &str) -> Result<(Schema, Vec<Row>), Box<dyn Error>> {
+    let reader = CsvReader { null_policy: NullPolicy::FillText("?".to_string()), ..CsvReader::new() };
+    let data = reader.read(file_path)?;
+    report_malformed(file_path, &data.malformed);
+    Ok((data.schema, data.rows))
+}
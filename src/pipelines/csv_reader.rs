@@ -0,0 +1,188 @@
+use std::error::Error;
+use csv::{ReaderBuilder, StringRecord};
+use crate::types::row::Row;
+use crate::types::row_value::RowValue;
+use crate::types::schema::{LogicalType, Schema};
+
+// what happens with cells that hold one of the null tokens, rows with a null are rejected unless a fill is chosen
+#[derive(Clone, Debug, PartialEq)]
+pub enum NullPolicy {
+    // the null becomes the zero value of the column type: 0, 0.0 or the empty string,
+    // so it can not be told apart from a real zero
+    Zero,
+    // the null becomes the given value for text columns and the zero value for numeric columns,
+    // e.g. Text("?") keeps the adult dataset's unknown categories as their own category
+    FillText(String),
+    // rows with a null are dropped and reported as malformed
+    Reject,
+}
+
+// a row that could not be read, line is the line number in the file (the header is line 1)
+#[derive(Clone, Debug, PartialEq)]
+pub struct MalformedRow {
+    pub line: usize,
+    pub reason: String,
+}
+
+pub struct CsvData {
+    pub schema: Schema,
+    pub rows: Vec<Row>,
+    pub malformed: Vec<MalformedRow>,
+}
+
+// Generic csv reader. The type of every column is inferred from the first sample_size rows: a column is Float if all
+// of its non-null sample cells parse as f64, otherwise Text. Integer columns have to be requested with an override.
+// Every cell of a row is parsed with the type of its column, rows that don't fit are reported in CsvData::malformed
+// and left out instead of being coerced.
+pub struct CsvReader {
+    pub null_tokens: Vec<String>,
+    pub null_policy: NullPolicy,
+    // (column name, type), wins over the inferred type
+    pub type_overrides: Vec<(String, LogicalType)>,
+    pub sample_size: usize,
+}
+
+impl CsvReader {
+    pub fn new() -> Self {
+        Self::new_with_types(vec![])
+    }
+
+    pub fn new_with_types(type_overrides: Vec<(String, LogicalType)>) -> Self {
+        Self {
+            null_tokens: vec!["".to_string(), "?".to_string(), "NA".to_string()],
+            null_policy: NullPolicy::Reject,
+            type_overrides,
+            sample_size: 1000,
+        }
+    }
+
+    pub fn read(&self, file_path: &str) -> Result<CsvData, Box<dyn Error>> {
+        let mut rdr = ReaderBuilder::new().flexible(true).from_path(file_path)?;
+        let names: Vec<String> = rdr.headers()?.iter().map(|h| h.trim().to_string()).collect();
+
+        // records the csv parser rejects (e.g. invalid utf-8) are reported like the rows that don't fit the schema
+        let mut records: Vec<StringRecord> = Vec::new();
+        let mut malformed: Vec<MalformedRow> = Vec::new();
+        for result in rdr.records() {
+            match result {
+                Ok(record) => records.push(record),
+                Err(err) => malformed.push(MalformedRow { line: err.position().map_or(0, |p| p.line() as usize), reason: err.to_string() }),
+            }
+        }
+        let schema = self.infer_schema(names, &records);
+
+        let mut rows: Vec<Row> = Vec::new();
+        for record in records.iter() {
+            match self.parse_record(&schema, record) {
+                Ok(row) => rows.push(row),
+                Err(reason) => malformed.push(MalformedRow { line: record.position().map_or(0, |p| p.line() as usize), reason }),
+            }
+        }
+        malformed.sort_by_key(|row| row.line);
+        Ok(CsvData { schema, rows, malformed })
+    }
+
+    fn is_null(&self, cell: &str) -> bool {
+        self.null_tokens.iter().any(|token| token == cell)
+    }
+
+    pub(crate) fn infer_schema(&self, names: Vec<String>, records: &[StringRecord]) -> Schema {
+        let sample = &records[..records.len().min(self.sample_size)];
+        Schema::new(names.into_iter().enumerate()
+            .map(|(col_id, name)| {
+                let logical_type = match self.type_overrides.iter().find(|(n, _)| *n == name) {
+                    Some((_, logical_type)) => *logical_type,
+                    None => {
+                        let numeric = sample.iter()
+                            .filter_map(|record| record.get(col_id).map(str::trim))
+                            .filter(|cell| !self.is_null(cell))
+                            .all(|cell| cell.parse::<f64>().is_ok());
+                        if numeric { LogicalType::Float } else { LogicalType::Text }
+                    }
+                };
+                (name, logical_type)
+            })
+            .collect())
+    }
+
+    pub(crate) fn parse_record(&self, schema: &Schema, record: &StringRecord) -> Result<Row, String> {
+        if record.len() != schema.len() {
+            return Err(format!("expected {} fields, found {}", schema.len(), record.len()));
+        }
+        let values = record.iter().enumerate()
+            .map(|(col_id, cell)| self.parse_cell(schema, col_id, cell.trim()))
+            .collect::<Result<Vec<RowValue>, String>>()?;
+        Ok(Row::with_row_values(values))
+    }
+
+    fn parse_cell(&self, schema: &Schema, col_id: usize, cell: &str) -> Result<RowValue, String> {
+        let logical_type = schema.logical_type(col_id);
+        if self.is_null(cell) {
+            return match (&self.null_policy, logical_type) {
+                (NullPolicy::Reject, _) => Err(format!("null in column {}", schema.name(col_id))),
+                (NullPolicy::FillText(fill), LogicalType::Text) => Ok(RowValue::Text(fill.clone())),
+                (_, LogicalType::Integer) => Ok(RowValue::Integer(0)),
+                (_, LogicalType::Float) => Ok(RowValue::Float(0.0)),
+                (_, LogicalType::Text) => Ok(RowValue::Text(String::new())),
+            };
+        }
+        let parsed = match logical_type {
+            LogicalType::Integer => cell.parse::<i64>().map(RowValue::Integer).ok(),
+            LogicalType::Float => cell.parse::<f64>().map(RowValue::Float).ok(),
+            LogicalType::Text => Some(RowValue::Text(cell.to_string())),
+        };
+        parsed.ok_or_else(|| format!("{:?} is not a valid {:?} for column {}", cell, logical_type, schema.name(col_id)))
+    }
+}
+
+// prints a summary of the malformed rows, the readers of the demo pipelines go on with the remaining rows
+pub(crate) fn report_malformed(file_path: &str, malformed: &[MalformedRow]) {
+    if malformed.is_empty() {
+        return;
+    }
+    eprintln!("{}: skipped {} malformed rows", file_path, malformed.len());
+    for row in malformed.iter().take(10) {
+        eprintln!("  line {}: {}", row.line, row.reason);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::*;
+    #[test]
+    fn csv_reader_infers_types_and_reports_malformed_rows() {
+        let path = std::env::temp_dir().join(format!("csv_reader_test_{}.csv", std::process::id()));
+        fs::write(&path, "age, workclass, hours\n39, State-gov, 40\n50, ?, 13\n38, Private\n53, 7, x\n").unwrap();
+
+        let reader = CsvReader { null_policy: NullPolicy::FillText("?".to_string()), sample_size: 2, ..CsvReader::new() };
+        let data = reader.read(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(data.schema.columns, vec![
+            ("age".to_string(), LogicalType::Float),
+            ("workclass".to_string(), LogicalType::Text),
+            ("hours".to_string(), LogicalType::Float),
+        ]);
+        assert_eq!(data.rows.len(), 2);
+        assert_eq!(data.rows[1].get(&data.schema, "workclass"), Some(&RowValue::Text("?".to_string())));
+        // the short row and the row with text in a numeric column are reported, not coerced
+        assert_eq!(data.malformed.iter().map(|row| row.line).collect::<Vec<_>>(), vec![4, 5]);
+    }
+
+    #[test]
+    fn csv_reader_rejects_nulls_and_unreadable_rows() {
+        let path = std::env::temp_dir().join(format!("csv_reader_reject_test_{}.csv", std::process::id()));
+        let mut content = b"age, note\n39,\"two\nlines\"\n50, NA\n".to_vec();
+        content.extend_from_slice(b"38, \xff\n53, ok\n");
+        fs::write(&path, content).unwrap();
+
+        let data = CsvReader::new().read(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(data.rows.len(), 2);
+        // the lines are counted in the file, so the quoted line break moves the rows behind it.
+        // the null and the invalid utf-8 are reported instead of filled in or aborting the read
+        assert_eq!(data.malformed.iter().map(|row| row.line).collect::<Vec<_>>(), vec![4, 5]);
+    }
+}
@@ -0,0 +1,272 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::time::{Duration, Instant};
+use csv::{ReaderBuilder, StringRecord};
+use differential_dataflow::input::InputSession;
+use timely::communication::Allocate;
+use timely::dataflow::ProbeHandle;
+use timely::worker::Worker;
+use crate::pipelines::csv_reader::CsvReader;
+use crate::types::row::Row;
+use crate::types::schema::Schema;
+
+// when a batch of rows is closed, every batch gets its own timestamp
+#[derive(Clone, Debug, PartialEq)]
+pub enum BatchPolicy {
+    // a fixed number of rows per timestamp
+    Rows(usize),
+    // all rows read within the interval, the batch is closed at the first row boundary after the interval
+    // even if it is empty, so a slow file doesn't hold back the timestamps
+    Interval(Duration),
+    // all rows that start within the next n bytes of the shard, e.g. a fraction of shard_len for an initial batch
+    Bytes(u64),
+}
+
+// Streams a csv file into an InputSession without loading it into memory. Every worker reads its own byte range of
+// the file, a row belongs to the worker whose range holds its first byte. The rows of a shard are numbered from 0,
+// the row id of the i-th row of worker w is i * peers + w, so the ids are unique and compact (e.g. for the matrix sinks).
+// The schema is inferred from the first rows of the file by every worker, so all shards agree on it.
+// Rows must not contain line breaks inside quoted fields, otherwise a shard can start in the middle of a row.
+pub struct CsvSource {
+    records: csv::Reader<BufReader<File>>,
+    // byte offset of the first row of the shard, positions of the csv reader are relative to it
+    begin: u64,
+    end: u64,
+    parser: CsvReader,
+    schema: Schema,
+    batch_policy: BatchPolicy,
+    // (row id, reason)
+    malformed: Vec<(usize, String)>,
+    worker_index: usize,
+    peers: usize,
+    // number of rows of the shard read so far, malformed ones included
+    rows_read: usize,
+    done: bool,
+}
+
+impl CsvSource {
+    pub fn new(file_path: &str, worker_index: usize, peers: usize, batch_policy: BatchPolicy) -> Result<Self, Box<dyn Error>> {
+        Self::new_with_reader(file_path, worker_index, peers, batch_policy, CsvReader::new())
+    }
+
+    // the reader sets the null tokens, null policy, type overrides and sample size
+    pub fn new_with_reader(file_path: &str, worker_index: usize, peers: usize, batch_policy: BatchPolicy, parser: CsvReader) -> Result<Self, Box<dyn Error>> {
+        let mut rdr = ReaderBuilder::new().flexible(true).from_path(file_path)?;
+        let names: Vec<String> = rdr.headers()?.iter().map(|h| h.trim().to_string()).collect();
+        let header_end = rdr.position().byte();
+        // unreadable records are left out of the sample, feed reports them
+        let sample: Vec<StringRecord> = rdr.records().take(parser.sample_size).filter_map(Result::ok).collect();
+        let schema = parser.infer_schema(names, &sample);
+
+        let file_len = std::fs::metadata(file_path)?.len();
+        let body = file_len.saturating_sub(header_end);
+        let start = header_end + body * worker_index as u64 / peers as u64;
+        let end = header_end + body * (worker_index as u64 + 1) / peers as u64;
+
+        // the row that spans the start of the range belongs to the previous shard
+        let mut file = BufReader::new(File::open(file_path)?);
+        let mut begin = start;
+        if start > header_end {
+            file.seek(SeekFrom::Start(start - 1))?;
+            begin = start - 1 + file.read_until(b'\n', &mut Vec::new())? as u64;
+        }
+        // the first shard starts right after the header
+        file.seek(SeekFrom::Start(begin))?;
+        let records = ReaderBuilder::new().has_headers(false).flexible(true).from_reader(file);
+
+        Ok(Self { records, begin, end, parser, schema, batch_policy, malformed: Vec::new(), worker_index, peers, rows_read: 0, done: false })
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    // rows of the shard that could not be read so far
+    pub fn malformed(&self) -> &[(usize, String)] {
+        &self.malformed
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    // number of bytes of the file the shard covers
+    pub fn shard_len(&self) -> u64 {
+        self.end.saturating_sub(self.begin)
+    }
+
+    // the policy of the following batches, e.g. single rows after a large initial batch
+    pub fn set_batch_policy(&mut self, batch_policy: BatchPolicy) {
+        self.batch_policy = batch_policy;
+    }
+
+    // inserts the next batch, then advances the input to the next timestamp and flushes it,
+    // returns the number of inserted rows
+    pub fn feed(&mut self, input: &mut InputSession<usize, (usize, Row), isize>) -> Result<usize, Box<dyn Error>> {
+        let batch_start = (Instant::now(), self.records.position().byte());
+        let mut inserted = 0;
+        while !self.batch_full(inserted, batch_start) {
+            match self.next_record()? {
+                None => break,
+                Some((row_id, record)) => match self.parser.parse_record(&self.schema, &record) {
+                    Ok(row) => {
+                        input.insert((row_id, row));
+                        inserted += 1;
+                    }
+                    Err(reason) => self.malformed.push((row_id, reason)),
+                },
+            }
+        }
+        let next_time = input.time() + 1;
+        input.advance_to(next_time);
+        input.flush();
+        Ok(inserted)
+    }
+
+    // feeds the whole shard batch by batch, waits for every batch before reading the next one and closes the input
+    // at the end, so the workers with longer shards are not held back
+    pub fn feed_all<A: Allocate>(&mut self, worker: &mut Worker<A>, mut input: InputSession<usize, (usize, Row), isize>, probe: &ProbeHandle<usize>) -> Result<(), Box<dyn Error>> {
+        while !self.is_done() {
+            self.feed(&mut input)?;
+            worker.step_while(|| probe.less_than(input.time()));
+        }
+        input.close();
+        Ok(())
+    }
+
+    fn batch_full(&self, inserted: usize, (start_time, start_byte): (Instant, u64)) -> bool {
+        match &self.batch_policy {
+            BatchPolicy::Rows(n) => inserted >= (*n).max(1),
+            BatchPolicy::Interval(interval) => start_time.elapsed() >= *interval,
+            BatchPolicy::Bytes(n) => self.records.position().byte() - start_byte >= (*n).max(1),
+        }
+    }
+
+    fn next_record(&mut self) -> Result<Option<(usize, StringRecord)>, Box<dyn Error>> {
+        if self.done {
+            return Ok(None);
+        }
+        let mut record = StringRecord::new();
+        loop {
+            match self.records.read_record(&mut record) {
+                Ok(true) => break,
+                Ok(false) => {
+                    self.done = true;
+                    return Ok(None);
+                }
+                // a record the csv parser rejects (e.g. invalid utf-8) is reported and skipped
+                Err(err) => match err.position() {
+                    Some(position) if self.begin + position.byte() < self.end => {
+                        let row_id = self.next_row_id();
+                        self.malformed.push((row_id, err.to_string()));
+                    }
+                    Some(_) => {
+                        self.done = true;
+                        return Ok(None);
+                    }
+                    None => return Err(err.into()),
+                },
+            }
+        }
+        let offset = self.begin + record.position().map(|p| p.byte()).unwrap_or(0);
+        if offset >= self.end {
+            self.done = true;
+            return Ok(None);
+        }
+        Ok(Some((self.next_row_id(), record)))
+    }
+
+    fn next_row_id(&mut self) -> usize {
+        let row_id = self.rows_read * self.peers + self.worker_index;
+        self.rows_read += 1;
+        row_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+    use super::*;
+
+    // (row id, first value) of every row the workers inserted
+    fn read_sharded(file_path: &str, peers: usize) -> Vec<(usize, f64)> {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let output_clone = Arc::clone(&output);
+        let file_path = file_path.to_string();
+        let result = timely::execute(timely::Config::process(peers), move |worker| {
+            let mut input: InputSession<usize, (usize, Row), isize> = InputSession::new();
+            let output = Arc::clone(&output_clone);
+            let probe = worker.dataflow(|scope| {
+                input.to_collection(scope)
+                    .inspect(move |((row_id, row), _, _)| {
+                        output.lock().unwrap().push((*row_id, row.values[0].get_float()));
+                    })
+                    .probe()
+            });
+            let mut source = CsvSource::new(&file_path, worker.index(), worker.peers(), BatchPolicy::Rows(3)).unwrap();
+            source.feed_all(worker, input, &probe).unwrap();
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+        let mut output = output.lock().unwrap().clone();
+        output.sort_by_key(|(row_id, _)| *row_id);
+        output
+    }
+
+    #[test]
+    fn shards_cover_every_row_once() {
+        let path = std::env::temp_dir().join(format!("csv_source_test_{}.csv", std::process::id()));
+        let lines: Vec<String> = (0..20).map(|i| format!("{}, {}\n", i, if i % 2 == 0 { "a" } else { "b" })).collect();
+        fs::write(&path, format!("id, label\n{}", lines.concat())).unwrap();
+        let expected_values: Vec<f64> = (0..20).map(|i| i as f64).collect();
+
+        for peers in [1, 2] {
+            let mut output = read_sharded(path.to_str().unwrap(), peers);
+            output.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            assert_eq!(output.iter().map(|(_, value)| *value).collect::<Vec<_>>(), expected_values, "rows with {} workers", peers);
+            // every worker numbers its rows in file order, the i-th row of worker w has the id i * peers + w
+            for w in 0..peers {
+                let ids: Vec<usize> = output.iter().map(|(row_id, _)| *row_id).filter(|row_id| row_id % peers == w).collect();
+                let expected_ids: Vec<usize> = (0..ids.len()).map(|i| i * peers + w).collect();
+                assert!(!ids.is_empty(), "worker {} of {} read no rows", w, peers);
+                assert_eq!(ids, expected_ids, "row ids of worker {} of {}", w, peers);
+            }
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn byte_batches_cover_a_fraction_of_the_shard() {
+        let path = std::env::temp_dir().join(format!("csv_source_bytes_test_{}.csv", std::process::id()));
+        // every row takes up 6 bytes
+        let lines: Vec<String> = (0..10).map(|i| format!("{:02}, a\n", i)).collect();
+        fs::write(&path, format!("id, label\n{}", lines.concat())).unwrap();
+        let file_path = path.to_str().unwrap().to_string();
+        let result = timely::execute(timely::Config::process(1), move |worker| {
+            let mut input: InputSession<usize, (usize, Row), isize> = InputSession::new();
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let output_clone = Arc::clone(&output);
+            let probe = worker.dataflow(|scope| {
+                input.to_collection(scope)
+                    .inspect(move |((_, row), time, _)| {
+                        output_clone.lock().unwrap().push((*time, row.values[0].get_float()));
+                    })
+                    .probe()
+            });
+            let mut source = CsvSource::new(&file_path, worker.index(), worker.peers(), BatchPolicy::Rows(1)).unwrap();
+            assert_eq!(source.shard_len(), 60);
+            source.set_batch_policy(BatchPolicy::Bytes(source.shard_len() * 3 / 10));
+            assert_eq!(source.feed(&mut input).unwrap(), 3);
+            source.set_batch_policy(BatchPolicy::Rows(1));
+            source.feed_all(worker, input, &probe).unwrap();
+
+            let mut output = output.lock().unwrap().clone();
+            output.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let expected: Vec<(usize, f64)> = (0..10).map(|i| (i.max(2) - 2, i as f64)).collect();
+            assert_eq!(output, expected, "Rows were not batched by bytes");
+        });
+        assert!(result.is_ok(), "Timely execution failed");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,21 +1,16 @@
 use std::error::Error;
-use csv::Reader;
+use crate::pipelines::csv_reader::{report_malformed, CsvReader};
 use crate::types::row::Row;
-use crate::types::row_value::RowValue;
+use crate::types::schema::Schema;
 
 // CSV Reader for csv with floats returns Vec<Row>
 pub fn read_csv2(file_path: &str) -> Result<Vec<Row>, Box<dyn Error>> {
-    let mut rdr = Reader::from_path(file_path)?;
-    let headers = rdr.headers()?;
-    println!("Headers: {:?}", headers);
+    read_csv2_with_schema(file_path).map(|(_schema, rows)| rows)
+}
 
-    let mut rows: Vec<Row> = Vec::new(); // Array to store rows
-    for result in rdr.records() {
-        let record = result?;
-        let r_vals: Vec<RowValue> = record.iter()
-            .map(|s| RowValue::Float(s.trim().parse::<f64>().unwrap_or(-1.0)) ) // Trim spaces & convert to f64
-            .collect();
-        rows.push(Row::with_row_values(r_vals));
-    }
-    Ok(rows)
-}
\ No newline at end of file
+// same as read_csv2, rows with cells that are not numbers are reported and skipped
+pub fn read_csv2_with_schema(file_path: &str) -> Result<(Schema, Vec<Row>), Box<dyn Error>> {
+    let data = CsvReader::new().read(file_path)?;
+    report_malformed(file_path, &data.malformed);
+    Ok((data.schema, data.rows))
+}
@@ -2,3 +2,6 @@ pub mod pipeline_2;
 pub mod adult_dataset_reader;
 pub mod pipeline_3_diabetes;
 pub mod diabetes_dataset_reader;
+pub mod csv_reader;
+pub mod csv_source;
+pub mod parallel_fit_benchmark;
@@ -0,0 +1,74 @@
+use std::time::Instant;
+use differential_dataflow::input::InputSession;
+use crate::feature_encoders::column_encoder::ColumnEncoder;
+use crate::feature_encoders::multi_column_encoder::multi_column_encoder_with_ids;
+use crate::feature_encoders::one_hot_encoder::OneHotEncoder;
+use crate::feature_encoders::standard_scaler::StandardScaler;
+use crate::pipelines::csv_reader::{CsvReader, NullPolicy};
+use crate::pipelines::csv_source::{BatchPolicy, CsvSource};
+use crate::pipelines::pipeline_3_diabetes::print_demo_separator;
+use crate::types::schema::{ColumnSelector, LogicalType};
+
+// Fits a StandardScaler on every numerical and, in a separate run, a OneHotEncoder on every categorical column of the
+// adult dataset with each of the worker counts (e.g. 1, 2, 4, 8). Every worker only reads its own part of the file.
+// The scaler timings show how well the two stage fitting (operators::parallel_fit) scales with the number of workers.
+// The one-hot positions are found in parallel but assigned in a single final stage (see assign_positions), the
+// one-hot timings show how much that final stage holds back the scaling.
+pub fn run_parallel_fit_benchmark(file_path: &str, worker_counts: Vec<usize>, size: f32) {
+    println!("PARALLEL FIT BENCHMARK\n");
+    for (workers, one_hot) in worker_counts.iter().flat_map(|&workers| [(workers, false), (workers, true)]) {
+        let file_path = file_path.to_string();
+        let timer = Instant::now();
+        timely::execute(timely::Config::process(workers), move |worker| {
+            let reader = CsvReader { null_policy: NullPolicy::FillText("?".to_string()), ..CsvReader::new() };
+            let mut source = CsvSource::new_with_reader(&file_path, worker.index(), worker.peers(), BatchPolicy::Rows(1), reader)
+                .expect("could not read the adult dataset");
+            let schema = source.schema().clone();
+            let target_column = ColumnSelector::Name("salary".to_string());
+            let categorical_columns = schema.select(&ColumnSelector::Except(
+                Box::new(ColumnSelector::Type(LogicalType::Text)),
+                Box::new(target_column)));
+            let numerical_columns = schema.select(&ColumnSelector::Numeric);
+
+            let mut input = InputSession::new();
+            let probe = worker.dataflow(|scope| {
+                let input_df = input.to_collection(scope);
+                let config: Vec<(usize, Box<dyn ColumnEncoder<_>>)> = if one_hot {
+                    categorical_columns.iter()
+                        .map(|&i| (i, Box::new(OneHotEncoder::new()) as Box<dyn ColumnEncoder<_>>))
+                        .collect()
+                } else {
+                    numerical_columns.iter()
+                        .map(|&i| (i, Box::new(StandardScaler::new()) as Box<dyn ColumnEncoder<_>>))
+                        .collect()
+                };
+
+                multi_column_encoder_with_ids(&input_df, config)
+                    .probe()
+            });
+
+            // the first size fraction of the shard at time 0, the rest of it as a single update at time 1
+            source.set_batch_policy(BatchPolicy::Bytes((source.shard_len() as f64 * size as f64) as u64));
+            source.feed(&mut input).expect("could not read the adult dataset");
+            let timer_init = Instant::now();
+            worker.step_while(|| probe.less_than(input.time()));
+            let init = timer_init.elapsed().as_micros();
+
+            source.set_batch_policy(BatchPolicy::Bytes(source.shard_len()));
+            source.feed(&mut input).expect("could not read the adult dataset");
+            let timer_updates = Instant::now();
+            worker.step_while(|| probe.less_than(input.time()));
+            if worker.index() == 0 {
+                println!("Workers: {}, {}: Init Computation took: {:?}, Updates took: {:?}",
+                         worker.peers(), fitted(one_hot), init, timer_updates.elapsed().as_micros());
+            }
+        }).expect("Computation terminated abnormally");
+        println!("Workers: {}, {}: Total time: {:?}", workers, fitted(one_hot), timer.elapsed().as_micros());
+    }
+
+    print_demo_separator()
+}
+
+fn fitted(one_hot: bool) -> &'static str {
+    if one_hot { "OneHotEncoder" } else { "StandardScaler" }
+}
@@ -1,82 +1,95 @@
-use std::collections::HashSet;
 use std::time::Instant;
 use differential_dataflow::input::InputSession;
 use crate::feature_encoders::column_encoder::ColumnEncoder;
-use crate::feature_encoders::multi_column_encoder::multi_column_encoder;
+use crate::feature_encoders::feature_selection::proxy_detector::{ColumnKind, ProxyDetector, ProxyGate};
+use crate::feature_encoders::multi_column_encoder::multi_column_encoder_with_ids;
 use crate::feature_encoders::one_hot_encoder::OneHotEncoder;
 use crate::feature_encoders::passthrough::Passthrough;
+use crate::feature_encoders::pipeline::Pipeline;
+use crate::pipelines::csv_reader::{CsvReader, NullPolicy};
+use crate::pipelines::csv_source::{BatchPolicy, CsvSource};
 use crate::pipelines::pipeline_3_diabetes::print_demo_separator;
-use crate::types::row::Row;
-use crate::types::row_value::RowValue;
+use crate::types::schema::{ColumnSelector, LogicalType};
 
-fn difference(a: &[usize], b: &[usize]) -> Vec<usize> {
-    let set_b: HashSet<_> = b.iter().collect();
-    a.iter().filter(|&&x| !set_b.contains(&x)).copied().collect()
-}
+const PROXY_THRESHOLD: f64 = 0.3;
 
-pub fn run_pipeline2(dataset: Vec<Row>, r1: i32, r2: i32, size: f32) {
+pub fn run_pipeline2(file_path: &str, r1: i32, r2: i32, size: f32) {
     println!("PIPELINE 2\n");
-    //let dataset = read_adult_csv("data/adult_data.csv").unwrap();
+    let file_path = file_path.to_string();
     let timer = Instant::now();
-    let protected_attributes = [8, 9].to_vec();
-    let proxy_attributes = [12].to_vec(); //hardcoded instead of computed
-    let split = (dataset.len()as f32*size) as usize;
 
-    let mut excluded_attributes: Vec<usize> = protected_attributes
-        .iter()
-        .chain(proxy_attributes.iter())
-        .copied()
-        .collect();
-    excluded_attributes.push(14); //exclude target column salary
+    // every worker streams its own part of the file, the first size fraction of it as the initial batch
+    // and the remaining rows one per timestamp
+    timely::execute_from_args(std::env::args(), move |worker| {
+        let reader = CsvReader { null_policy: NullPolicy::FillText("?".to_string()), ..CsvReader::new() };
+        let mut source = CsvSource::new_with_reader(&file_path, worker.index(), worker.peers(), BatchPolicy::Rows(1), reader)
+            .expect("could not read the adult dataset");
+        let schema = source.schema().clone();
+        let protected_attributes = schema.select(&ColumnSelector::Names(vec!["race".to_string(), "sex".to_string()]));
 
-    let categorical_columns = difference(
-        &(&dataset[0]).find_indices(|v| matches!(v, RowValue::Text(_))),
-        &excluded_attributes,
-    );
-    let numerical_columns = difference(
-        &(&dataset[0]).find_indices(|v| !matches!(v, RowValue::Text(_))),
-        &excluded_attributes,
-    );
+        // the protected attributes and the target column salary
+        let excluded = ColumnSelector::Names(vec!["race".to_string(), "sex".to_string(), "salary".to_string()]);
+        let categorical_columns = schema.select(&ColumnSelector::Except(
+            Box::new(ColumnSelector::Type(LogicalType::Text)),
+            Box::new(excluded.clone())));
+        let numerical_columns = schema.select(&ColumnSelector::Except(
+            Box::new(ColumnSelector::Numeric),
+            Box::new(excluded)));
 
+        let column_kind = |i: usize| match schema.logical_type(i) {
+            LogicalType::Text => ColumnKind::Categorical,
+            _ => ColumnKind::Numerical,
+        };
+        let protected: Vec<(usize, ColumnKind)> = protected_attributes.iter()
+            .map(|&i| (i, column_kind(i)))
+            .collect();
+        // proxy attributes are computed from all remaining columns and excluded as soon as they cross the threshold
+        let candidates: Vec<(usize, ColumnKind)> = categorical_columns.iter()
+            .chain(numerical_columns.iter())
+            .map(|&i| (i, column_kind(i)))
+            .collect();
 
-    timely::execute_from_args(std::env::args(), move |worker| {
         let mut input = InputSession::new();
         let probe = worker.dataflow(|scope| {
-            let mut input_df = input.to_collection(scope);
+            let input_df = input.to_collection(scope);
+            let proxies = ProxyDetector::new(protected, candidates, PROXY_THRESHOLD)
+                .detect(&input_df);
 
             let config: Vec<(usize, Box<dyn ColumnEncoder<_>>)> = categorical_columns.iter()
-                .map(|&i| (i, Box::new(OneHotEncoder::new()) as Box<dyn ColumnEncoder<_>>))
-                .chain(numerical_columns.iter().map(|&i| (i, Box::new(Passthrough::new()) as Box<dyn ColumnEncoder<_>>)))
+                .map(|&i| (i, Box::new(Pipeline::new(vec![
+                    Box::new(OneHotEncoder::new()),
+                    Box::new(ProxyGate::new(i, &proxies))
+                ])) as Box<dyn ColumnEncoder<_>>))
+                .chain(numerical_columns.iter().map(|&i| (i, Box::new(Pipeline::new(vec![
+                    Box::new(Passthrough::new()),
+                    Box::new(ProxyGate::new(i, &proxies))
+                ])) as Box<dyn ColumnEncoder<_>>)))
                 .collect();
 
-            multi_column_encoder(&input_df, config)
+            multi_column_encoder_with_ids(&input_df, config)
                 .probe()
         });
-        input.advance_to(0);
-        for rix in 0..split {
-            input.insert((rix, dataset[rix].clone()));
-        }
-        input.advance_to(1);
-        input.flush();
+        source.set_batch_policy(BatchPolicy::Bytes((source.shard_len() as f64 * size as f64) as u64));
+        source.feed(&mut input).expect("could not read the adult dataset");
         //println!("\n-- time 0 -> 1 --------------------");
         let timer1 = Instant::now();
         worker.step_while(|| probe.less_than(input.time()));
         println!("\nInit Computation took: {:?}", timer1.elapsed().as_micros());
-        println!("Number of Updates: {}", dataset.len() - split);
-        let mut time = 2;
-        for rix in split..dataset.len() {
-            input.insert((rix, dataset[rix].clone()));
-            input.advance_to(time);
-            input.flush();
-            //println!("\n-- time {} -> {} --------------------", time-1, time);
-            time += 1;
-            let timer_tmp = Instant::now();
+        source.set_batch_policy(BatchPolicy::Rows(1));
+        let mut updates = 0;
+        while !source.is_done() {
+            updates += source.feed(&mut input).expect("could not read the adult dataset");
+            //println!("\n-- time {} -> {} --------------------", input.time()-1, input.time());
+            //let timer_tmp = Instant::now();
             worker.step_while(|| probe.less_than(input.time()));
             //println!("{:?}", timer_tmp.elapsed().as_micros());
         }
+        input.close();
+        println!("Number of Updates: {}", updates);
         println!("\nComputation took: {:?}", timer1.elapsed().as_micros());
         // input.insert((7,Row::with_values(7, 2.0, "7".to_string())));
     }).expect("Computation terminated abnormally");
+    println!("\nTotal time: {:?}", timer.elapsed().as_micros());
 
     print_demo_separator()
-}
\ No newline at end of file
+}
@@ -1,19 +1,24 @@
 use std::time::Instant;
 use differential_dataflow::input::InputSession;
 use crate::feature_encoders::column_encoder::ColumnEncoder;
-use crate::feature_encoders::multi_column_encoder::multi_column_encoder;
+use crate::feature_encoders::multi_column_encoder::multi_column_encoder_with_ids;
 use crate::feature_encoders::standard_scaler::StandardScaler;
-use crate::types::row::Row;
+use crate::pipelines::csv_reader::CsvReader;
+use crate::pipelines::csv_source::{BatchPolicy, CsvSource};
 
 pub(crate) fn print_demo_separator() {
     println!("---------------------------------------------------------------------------");
 }
 
-pub fn diabetes(rows: Vec<Row>, r1: i32, r2: i32, size: f32) {
-    let cols = rows[0].size;
-    let split = (rows.len()as f32*size) as usize;
+// every worker streams its own part of the file, the first size fraction of it as the initial batch
+// and the remaining rows one per timestamp
+pub fn diabetes(file_path: &str, r1: i32, r2: i32, size: f32) {
+    let file_path = file_path.to_string();
     // Input: Tuple
     timely::execute_from_args(std::env::args(), move |worker| {
+        let mut source = CsvSource::new_with_reader(&file_path, worker.index(), worker.peers(), BatchPolicy::Rows(1), CsvReader::new())
+            .expect("could not read the diabetes dataset");
+        let cols = source.schema().len();
         let mut input = InputSession::new();
         let probe = worker.dataflow(|scope| {
             let input_df = input.to_collection(scope);
@@ -25,36 +30,31 @@ pub fn diabetes(rows: Vec<Row>, r1: i32, r2: i32, size: f32) {
                 config.push((col, Box::new(StandardScaler::new_with_rounding(r1, r2))));
             }
 
-            multi_column_encoder(&input_df, config)
+            multi_column_encoder_with_ids(&input_df, config)
                 //.inspect(|x| println!("OUT: {:?}", x))
                 .probe()
         });
 
-        input.advance_to(0);
-        for rix in 0..split {
-            input.insert((rix, rows[rix].clone()));
-        }
-        input.advance_to(1);
-        input.flush();
+        source.set_batch_policy(BatchPolicy::Bytes((source.shard_len() as f64 * size as f64) as u64));
+        source.feed(&mut input).expect("could not read the diabetes dataset");
         //println!("\n-- time 0 -> 1 --------------------");
         let timer1 = Instant::now();
         worker.step_while(|| probe.less_than(input.time()));
         println!("\nInit Computation took: {:?}", timer1.elapsed().as_micros());
-        println!("Number of Updates: {}", rows.len() - split);
-        let mut time = 2;
-        for rix in split..rows.len() {
-            input.insert((rix, rows[rix].clone()));
-            input.advance_to(time);
-            input.flush();
-            //println!("\n-- time {} -> {} --------------------", time-1, time);
-            time += 1;
+        source.set_batch_policy(BatchPolicy::Rows(1));
+        let mut updates = 0;
+        while !source.is_done() {
+            updates += source.feed(&mut input).expect("could not read the diabetes dataset");
+            //println!("\n-- time {} -> {} --------------------", input.time()-1, input.time());
             let timer_tmp = Instant::now();
             worker.step_while(|| probe.less_than(input.time()));
             println!("{:?}", timer_tmp.elapsed().as_micros());
         }
+        input.close();
+        println!("Number of Updates: {}", updates);
         println!("\nComputation took: {:?}", timer1.elapsed().as_micros());
         // input.insert((7,Row::with_values(7, 2.0, "7".to_string())));
     }).expect("Computation terminated abnormally");
 
     print_demo_separator()
-}
\ No newline at end of file
+}
@@ -3,6 +3,18 @@ use differential_dataflow::difference::{Abelian, IsZero, Monoid, Semigroup};
 use serde::{Deserialize, Serialize};
 use crate::types::safe_hash_map::SafeHashMap;
 
+// how the vector width (len) follows the number of distinct values, without a policy the width grows by 1.5x
+// and shrinks by 0.66x, compressing all positions (so every row changes)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum WidthPolicy {
+    // n positions plus one overflow bucket (position n) shared by all values that arrive when the positions are taken
+    FixedCapacity(usize),
+    // grows by 1.5x, never shrinks and never moves a position
+    Geometric,
+    // the width is the highest assigned position + 1, freed positions are reused before the width grows
+    Exact,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct PositionAssignmentAggregate<T>
 where T: Ord + Clone + Hash {
@@ -13,7 +25,8 @@ where T: Ord + Clone + Hash {
     next_index: usize,
     len : usize,
     neg : bool,
-    row_count : isize
+    row_count : isize,
+    width_policy: Option<WidthPolicy>,
 }
 
 
@@ -23,8 +36,9 @@ where T: Ord + Clone + Hash {
         (self.val_to_index, self.len)
     }
 
-    pub fn new_with_vec(tokens : &Vec<T>, mult : isize) -> Self {
+    pub fn new_with_vec(tokens : &Vec<T>, mult : isize, width_policy: Option<WidthPolicy>) -> Self {
         let mut agg = Self::zero();
+        agg.set_width_policy(width_policy);
         agg.row_count = mult;
         for token in tokens {
             agg.plus_equals_value_count(token, mult);
@@ -32,17 +46,37 @@ where T: Ord + Clone + Hash {
         agg
     }
 
-    pub fn new_with_val(val : &T, mult : isize) -> Self {
+    pub fn new_with_val(val : &T, mult : isize, width_policy: Option<WidthPolicy>) -> Self {
         let mut agg = Self::zero();
+        agg.set_width_policy(width_policy);
         agg.row_count = mult;
         agg.plus_equals_value_count(val, mult);
         agg
     }
 
 
+    fn set_width_policy(&mut self, width_policy: Option<WidthPolicy>) {
+        if self.width_policy.is_none() {
+            self.width_policy = width_policy;
+            if let Some(WidthPolicy::FixedCapacity(n)) = width_policy {
+                self.len = n + 1;
+            }
+        }
+    }
+
+    // position of the overflow bucket, only with a fixed capacity
+    fn overflow_index(&self) -> Option<usize> {
+        match self.width_policy {
+            Some(WidthPolicy::FixedCapacity(n)) => Some(n),
+            _ => None,
+        }
+    }
+
     fn assign_index(&mut self) -> usize {
         if self.free_indices.len() > 0 {
             self.free_indices.pop().unwrap()
+        } else if let Some(overflow) = self.overflow_index().filter(|n| self.next_index >= *n) {
+            overflow
         } else {
             self.next_index += 1;
             self.next_index-1
@@ -67,7 +101,10 @@ where T: Ord + Clone + Hash {
                 self.val_to_count.insert(value.clone(), count + count_to_add);
                 if count > 0 && count + count_to_add <= 0 {
                     let index = *self.val_to_index.get(&value).unwrap();
-                    self.free_indices.push(index);
+                    // the overflow bucket is shared, it is never handed out as a free position
+                    if Some(index) != self.overflow_index() {
+                        self.free_indices.push(index);
+                    }
                 } else if count <= 0 && count + count_to_add > 0 {
                     let new_index = self.assign_index();
                     self.val_to_index.insert(value.clone(), new_index);
@@ -81,6 +118,19 @@ where T: Ord + Clone + Hash {
                 }
             }
         }
+        match self.width_policy {
+            None => self.resize(),
+            Some(WidthPolicy::Geometric) => {
+                while self.value_count() > self.len {
+                    self.len = (self.len as f64 * 1.5).round() as usize;
+                }
+            }
+            Some(WidthPolicy::Exact) => self.len = self.next_index,
+            Some(WidthPolicy::FixedCapacity(_)) => {}
+        }
+    }
+
+    fn resize(&mut self) {
         if self.value_count() > self.len {
             while self.value_count() > self.len {
                 self.len = (self.len as f64 * 1.5).round() as usize;
@@ -112,6 +162,7 @@ where T: Ord + Clone + Hash {
 impl<T> Semigroup for PositionAssignmentAggregate<T>
 where T: Ord + Clone + Hash {
     fn plus_equals(&mut self, other: &Self) {
+        self.set_width_policy(other.width_policy);
         for (value, _) in other.val_to_index.iter() {
             let mut other_count = *other.val_to_count.get(value).unwrap();
             other_count =  if !(self.neg ^ other.neg) {other_count} else {-other_count};
@@ -125,7 +176,7 @@ where T: Ord + Clone + Hash {
 impl<T> Monoid for PositionAssignmentAggregate<T>
 where T: Ord + Clone + Hash {
     fn zero() -> Self {
-        Self { val_to_index: SafeHashMap::new(), val_to_count: SafeHashMap::new(), free_indices: Vec::new(), next_index: 0, neg: false, row_count: 0, len:1}
+        Self { val_to_index: SafeHashMap::new(), val_to_count: SafeHashMap::new(), free_indices: Vec::new(), next_index: 0, neg: false, row_count: 0, len:1, width_policy: None}
     }
 }
 
@@ -1,6 +1,7 @@
 pub mod row_value;
 pub mod row;
-pub(crate) mod safe_f64;
+pub mod schema;
+pub mod safe_f64;
 pub mod safe_vec;
 
 pub mod integer_assignment_aggregate;
@@ -1,6 +1,7 @@
 use std::cmp::Ordering;
 use serde::{Deserialize, Serialize};
 use crate::types::row_value::RowValue;
+use crate::types::schema::Schema;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Row {
@@ -52,6 +53,26 @@ impl Row {
     }
 
 
+    // the whole row as a single value, e.g. to feed it into a ColumnTransformer nested in a Pipeline
+    pub fn into_row_value(self) -> RowValue {
+        RowValue::Row(self.values)
+    }
+
+    // inverse of into_row_value, the positions of a (densified) vector become Float columns
+    pub fn from_row_value(value: RowValue) -> Self {
+        match value {
+            RowValue::Row(values) => Row::with_row_values(values),
+            RowValue::Vec(v) => Row::with_row_values(v.into_iter().map(RowValue::Float).collect()),
+            sparse @ RowValue::Sparse { .. } => Row::from_row_value(sparse.to_dense()),
+            val => Row::with_row_value(val),
+        }
+    }
+
+    // the value of the column with the given name
+    pub fn get(&self, schema: &Schema, name: &str) -> Option<&RowValue> {
+        schema.index_of(name).and_then(|col_id| self.values.get(col_id))
+    }
+
     pub fn find_indices<F>(&self, predicate: F) -> Vec<usize>
     where
         F: Fn(&RowValue) -> bool,
@@ -86,4 +107,19 @@ impl Ord for Row {
         // Compare rows based on their `values` vector
         self.values.iter().cmp(other.values.iter())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn row_values_convert_back_to_rows() {
+        let row = Row::with_values(7, 2.0, "seven".to_string());
+        assert_eq!(Row::from_row_value(row.clone().into_row_value()), row, "Row does not survive the round trip");
+
+        // vector positions become Float columns, sparse vectors are densified first
+        let expected = Row::with_row_values(vec![RowValue::Float(0.0), RowValue::Float(3.0), RowValue::Float(0.0)]);
+        assert_eq!(Row::from_row_value(RowValue::Vec(vec![0.0, 3.0, 0.0])), expected);
+        assert_eq!(Row::from_row_value(RowValue::sparse(3, vec![(1, 3.0)])), expected);
+        assert_eq!(Row::from_row_value(RowValue::Integer(4)), Row::with_row_value(RowValue::Integer(4)));
+    }
+}
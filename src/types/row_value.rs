@@ -10,6 +10,10 @@ pub enum RowValue {
     Text(String),
     Float(f64),
     Vec(Vec<f64>),
+    // a whole row, used to pass rows through column encoders (e.g. a ColumnTransformer nested in a Pipeline)
+    Row(Vec<RowValue>),
+    // a vector of length len that only stores its non-zero entries, indices are sorted ascending
+    Sparse { len: usize, indices: Vec<usize>, values: Vec<f64> },
 }
 
 
@@ -43,6 +47,62 @@ impl RowValue {
         }
     }
 
+    // builds a sparse vector from (index, value) pairs, zero values are dropped
+    pub fn sparse(len: usize, mut entries: Vec<(usize, f64)>) -> Self {
+        entries.retain(|(_, value)| *value != 0.0);
+        entries.sort_by_key(|(index, _)| *index);
+        let (indices, values) = entries.into_iter().unzip();
+        RowValue::Sparse { len, indices, values }
+    }
+
+    // a sparse vector that ends at its last non-zero entry, for encoders that publish their width separately
+    // (see ColumnEncoder::output_width), so a growing width doesn't change the rows
+    pub fn cells(entries: Vec<(usize, f64)>) -> Self {
+        let len = entries.iter()
+            .filter(|(_, value)| *value != 0.0)
+            .map(|(index, _)| index + 1)
+            .max()
+            .unwrap_or(0);
+        RowValue::sparse(len, entries)
+    }
+
+    // sparse vectors become dense vectors, every other value is returned as is
+    pub fn to_dense(&self) -> RowValue {
+        match self {
+            RowValue::Sparse { len, indices, values } => {
+                let mut vec = vec![0f64; *len];
+                for (index, value) in indices.iter().zip(values.iter()) {
+                    vec[*index] = *value;
+                }
+                RowValue::Vec(vec)
+            }
+            a => a.clone(),
+        }
+    }
+
+    // number of positions the value takes up in a vector, scalars take up one
+    pub fn vector_len(&self) -> usize {
+        match self {
+            RowValue::Vec(v) => v.len(),
+            RowValue::Sparse { len, .. } => *len,
+            RowValue::Integer(_) | RowValue::Float(_) => 1,
+            a => panic!("vector_len called on non-numeric row value [{:?}]", a),
+        }
+    }
+
+    // the (index, value) pairs of the non-zero positions, without densifying sparse vectors
+    pub fn nonzero_entries(&self) -> Vec<(usize, f64)> {
+        match self {
+            RowValue::Vec(v) => v.iter().copied().enumerate().filter(|(_, value)| *value != 0.0).collect(),
+            RowValue::Sparse { indices, values, .. } => indices.iter().copied().zip(values.iter().copied()).collect(),
+            RowValue::Integer(_) | RowValue::Float(_) => {
+                let value = self.get_float();
+                if value != 0.0 { vec![(0, value)] } else { vec![] }
+            }
+            a => panic!("nonzero_entries called on non-numeric row value [{:?}]", a),
+        }
+    }
+
     pub fn get_vec(&self) -> &Vec<f64> {
         match *self {
             RowValue::Vec(ref v) => {v}
@@ -50,8 +110,45 @@ impl RowValue {
         }
     }
 
+    // the value as part of a feature name, e.g. Private in workclass=Private
+    pub fn label(&self) -> String {
+        match self {
+            RowValue::Integer(i) => i.to_string(),
+            RowValue::Float(f) => f.to_string(),
+            RowValue::Text(s) => s.clone(),
+            RowValue::Row(values) => values.iter().map(|v| v.label()).collect::<Vec<_>>().join("_"),
+            a => format!("{:?}", a),
+        }
+    }
+
+    // the value as a vector of at least width positions, the missing positions are zero
+    pub fn padded(self, width: usize) -> RowValue {
+        match self {
+            RowValue::Sparse { len, indices, values } => RowValue::Sparse { len: len.max(width), indices, values },
+            RowValue::Vec(mut v) => {
+                if v.len() < width {
+                    v.resize(width, 0.0);
+                }
+                RowValue::Vec(v)
+            }
+            RowValue::Integer(_) | RowValue::Float(_) if width > 1 => RowValue::Vec(vec![self.get_float()]).padded(width),
+            a => a,
+        }
+    }
+
+    // appending a sparse vector makes the result sparse, so a single sparse segment keeps the whole vector sparse
     pub fn vector_append(self, other: &RowValue) -> RowValue{
         match self {
+            RowValue::Sparse { len, mut indices, mut values } => {
+                for (index, value) in other.nonzero_entries() {
+                    indices.push(len + index);
+                    values.push(value);
+                }
+                RowValue::Sparse { len: len + other.vector_len(), indices, values }
+            },
+            RowValue::Vec(v) if matches!(other, RowValue::Sparse { .. }) => {
+                RowValue::sparse(v.len(), v.into_iter().enumerate().collect()).vector_append(other)
+            },
             RowValue::Vec(mut v) => {
                 match other {
                     RowValue::Vec(v1) => {v.extend(v1); RowValue::Vec(v)}
@@ -97,6 +194,9 @@ impl PartialEq<Self> for RowValue {
             (RowValue::Float(a), RowValue::Float(b)) => a == b,
             (RowValue::Text(a), RowValue::Text(b)) => a == b,
             (RowValue::Vec(a), RowValue::Vec(b)) => a == b,
+            (RowValue::Row(a), RowValue::Row(b)) => a == b,
+            (RowValue::Sparse { len: l1, indices: i1, values: v1 }, RowValue::Sparse { len: l2, indices: i2, values: v2 }) =>
+                l1 == l2 && i1 == i2 && v1 == v2,
             _ => false,
         }
     }
@@ -109,6 +209,9 @@ impl PartialOrd<Self> for RowValue {
             (RowValue::Float(a), RowValue::Float(b)) => a.partial_cmp(b),
             (RowValue::Text(a), RowValue::Text(b)) => a.partial_cmp(b),
             (RowValue::Vec(a), RowValue::Vec(b)) => a.partial_cmp(b),
+            (RowValue::Row(a), RowValue::Row(b)) => a.partial_cmp(b),
+            (RowValue::Sparse { len: l1, indices: i1, values: v1 }, RowValue::Sparse { len: l2, indices: i2, values: v2 }) =>
+                (l1, i1).partial_cmp(&(l2, i2)).map(|ord| ord.then(v1.partial_cmp(v2).unwrap())),
             _ => panic!("Cannot compare RowValue of different types!"),
         }
     }
@@ -121,6 +224,9 @@ impl Ord for RowValue {
             (RowValue::Float(a), RowValue::Float(b)) => a.partial_cmp(b).expect("Comparison failed"),
             (RowValue::Text(a), RowValue::Text(b)) => a.cmp(b),
             (RowValue::Vec(a), RowValue::Vec(b)) => a.partial_cmp(b).unwrap(),
+            (RowValue::Row(a), RowValue::Row(b)) => a.cmp(b),
+            (RowValue::Sparse { len: l1, indices: i1, values: v1 }, RowValue::Sparse { len: l2, indices: i2, values: v2 }) =>
+                (l1, i1).cmp(&(l2, i2)).then(v1.partial_cmp(v2).expect("Comparison failed")),
             _ => panic!("Cannot compare RowValue of different types!"),
         }
     }
@@ -141,7 +247,19 @@ impl Hash for RowValue {
                 2.hash(state);
                 a.to_bits().hash(state);
             }
-            _ => panic!("Can only hash floats, integers and strings!"),
+            RowValue::Row(a) => {
+                3.hash(state);
+                a.hash(state);
+            }
+            RowValue::Sparse { len, indices, values } => {
+                4.hash(state);
+                len.hash(state);
+                indices.hash(state);
+                for value in values {
+                    value.to_bits().hash(state);
+                }
+            }
+            _ => panic!("Can only hash floats, integers, strings, rows and sparse vectors!"),
         }
     }
 
@@ -3,7 +3,7 @@ use std::hash::{Hash, Hasher};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub(crate) struct SafeF64(pub(crate) f64);
+pub struct SafeF64(pub f64);
 
 impl PartialEq for SafeF64 {
     fn eq(&self, other: &Self) -> bool {
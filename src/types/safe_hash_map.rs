@@ -19,6 +19,10 @@ impl<T: Ord + Clone + Hash + PartialEq, V: Clone + PartialEq> SafeHashMap<T, V>
         self.0.get(key)
     }
 
+    pub(crate) fn remove(&mut self, key: &T) -> Option<V> {
+        self.0.remove(key)
+    }
+
     pub(crate) fn keys(&self) -> impl Iterator<Item = &T> {
         self.0.keys()
     }
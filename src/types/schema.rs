@@ -0,0 +1,148 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::types::row::Row;
+use crate::types::row_value::RowValue;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LogicalType {
+    Integer,
+    Float,
+    Text,
+}
+
+impl LogicalType {
+    pub fn of(value: &RowValue) -> Self {
+        match value {
+            RowValue::Integer(_) => LogicalType::Integer,
+            RowValue::Text(_) => LogicalType::Text,
+            _ => LogicalType::Float,
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, LogicalType::Integer | LogicalType::Float)
+    }
+}
+
+// names and logical types of the columns of a Row, in column order
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Schema {
+    pub columns: Vec<(String, LogicalType)>,
+}
+
+impl Schema {
+    pub fn new(columns: Vec<(String, LogicalType)>) -> Self {
+        Self{columns}
+    }
+
+    // the types are taken from the values of a sample row, e.g. the first row of a csv file
+    pub fn infer(names: Vec<String>, row: &Row) -> Self {
+        Self::new(names.into_iter().zip(row.values.iter().map(LogicalType::of)).collect())
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.columns.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    pub fn name(&self, col_id: usize) -> &str {
+        &self.columns[col_id].0
+    }
+
+    pub fn logical_type(&self, col_id: usize) -> LogicalType {
+        self.columns[col_id].1
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|(n, _)| n == name)
+    }
+
+    // the indices of all columns the selector matches, in column order
+    pub fn select(&self, selector: &ColumnSelector) -> Vec<usize> {
+        (0..self.len()).filter(|&col_id| selector.matches(self, col_id)).collect()
+    }
+
+    // the names a selector refers to that are not columns of the schema, e.g. typos
+    pub fn unknown_names(&self, selector: &ColumnSelector) -> Vec<String> {
+        selector.names().into_iter().filter(|name| self.index_of(name).is_none()).collect()
+    }
+}
+
+// selects columns of a Schema like sklearn's make_column_selector, e.g. all text columns except the target:
+// ColumnSelector::Except(Box::new(ColumnSelector::Type(LogicalType::Text)), Box::new(ColumnSelector::Name("salary".to_string())))
+#[derive(Clone, Debug)]
+pub enum ColumnSelector {
+    Name(String),
+    Names(Vec<String>),
+    Type(LogicalType),
+    // Integer and Float columns
+    Numeric,
+    // columns whose name matches the regex
+    Pattern(Regex),
+    // columns that match every selector
+    All(Vec<ColumnSelector>),
+    // columns that match the first selector but not the second
+    Except(Box<ColumnSelector>, Box<ColumnSelector>),
+}
+
+impl ColumnSelector {
+    pub fn pattern(regex: &str) -> Result<Self, regex::Error> {
+        Ok(ColumnSelector::Pattern(Regex::new(regex)?))
+    }
+
+    // the column names of the Name and Names selectors, including the nested ones
+    pub fn names(&self) -> Vec<String> {
+        match self {
+            ColumnSelector::Name(n) => vec![n.clone()],
+            ColumnSelector::Names(names) => names.clone(),
+            ColumnSelector::All(selectors) => selectors.iter().flat_map(|s| s.names()).collect(),
+            ColumnSelector::Except(selected, excluded) => selected.names().into_iter().chain(excluded.names()).collect(),
+            _ => vec![],
+        }
+    }
+
+    pub fn matches(&self, schema: &Schema, col_id: usize) -> bool {
+        let name = schema.name(col_id);
+        match self {
+            ColumnSelector::Name(n) => n == name,
+            ColumnSelector::Names(names) => names.iter().any(|n| n == name),
+            ColumnSelector::Type(logical_type) => schema.logical_type(col_id) == *logical_type,
+            ColumnSelector::Numeric => schema.logical_type(col_id).is_numeric(),
+            ColumnSelector::Pattern(regex) => regex.is_match(name),
+            ColumnSelector::All(selectors) => selectors.iter().all(|s| s.matches(schema, col_id)),
+            ColumnSelector::Except(selected, excluded) =>
+                selected.matches(schema, col_id) && !excluded.matches(schema, col_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn selectors_resolve_to_column_indices() {
+        let row = Row::with_row_values(vec![
+            RowValue::Float(39.0),
+            RowValue::Text("State-gov".to_string()),
+            RowValue::Float(13.0),
+            RowValue::Text("Male".to_string()),
+            RowValue::Text("<=50K".to_string()),
+        ]);
+        let names = ["age", "workclass", "education-num", "sex", "salary"].iter().map(|n| n.to_string()).collect();
+        let schema = Schema::infer(names, &row);
+
+        let excluded = ColumnSelector::Names(vec!["sex".to_string(), "salary".to_string()]);
+        let categorical = ColumnSelector::Except(Box::new(ColumnSelector::Type(LogicalType::Text)), Box::new(excluded));
+        assert_eq!(schema.select(&categorical), vec![1]);
+        assert_eq!(schema.select(&ColumnSelector::Numeric), vec![0, 2]);
+        assert_eq!(schema.select(&ColumnSelector::pattern("^(age|sex)$").unwrap()), vec![0, 3]);
+        assert_eq!(row.get(&schema, "sex"), Some(&RowValue::Text("Male".to_string())));
+
+        let misspelled = ColumnSelector::Except(Box::new(ColumnSelector::Numeric), Box::new(ColumnSelector::Name("salery".to_string())));
+        assert_eq!(schema.unknown_names(&misspelled), vec!["salery".to_string()]);
+        assert!(schema.unknown_names(&categorical).is_empty());
+    }
+}